@@ -2,14 +2,23 @@
 //!
 //! This module defines the CLI structure and commands using the `clap` crate.
 //! It provides a simple interface for parsing command line arguments and
-//! returning the appropriate command to execute.
+//! returning the appropriate command to execute along with any global options.
 
-use clap::{Parser, Subcommand};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use serde::Deserialize;
+
+use crate::recur::Recurrence;
 
 /// Main CLI structure for the todo-rs application.
 ///
 /// This struct defines the overall command line interface using clap's derive API.
-/// It contains a single subcommand field that holds the specific action to perform.
+/// It contains the subcommand that holds the specific action to perform, plus
+/// global options that apply to every command.
 #[derive(Parser, Debug)]
 #[command(name = "todo-rs")]
 #[command(about = "A simple CLI To-Do app", long_about = None)]
@@ -17,53 +26,700 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "Output format for task listings [default: table]"
+    )]
+    pub format: Option<OutputFormat>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Use this database file instead of the default one"
+    )]
+    pub db: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        default_value = DEFAULT_PROFILE,
+        value_parser = parse_profile,
+        help = "Work with this task list; each profile has its own tasks in the same database"
+    )]
+    pub profile: String,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Print task tables in the plain fixed-width layout without borders"
+    )]
+    pub plain: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "COLUMNS",
+        default_value_t = 60,
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Widest description shown in task tables; longer ones are truncated or wrapped"
+    )]
+    pub max_width: u16,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "truncate",
+        help = "Wrap long descriptions in task tables onto extra lines instead of truncating them"
+    )]
+    pub wrap: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "wrap",
+        help = "Truncate long descriptions in task tables with an ellipsis (the default)"
+    )]
+    pub truncate: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "When to color task tables [default: auto]"
+    )]
+    pub color: Option<ColorChoice>,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Answer yes to every confirmation prompt (for scripts)"
+    )]
+    pub yes: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Show timestamps in task tables in UTC instead of local time"
+    )]
+    pub utc: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "FORMAT",
+        value_parser = parse_date_format,
+        help = "chrono format for timestamps in task tables, e.g. '%d/%m/%Y %H:%M' [default: %Y-%m-%d %H:%M:%S]"
+    )]
+    pub date_format: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Don't print success messages; add prints only the new task IDs"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Print the database path, every SQL statement and timings to stderr"
+    )]
+    pub verbose: bool,
+}
+
+/// Profile used when `--profile` is not given.
+///
+/// Tasks created before profiles existed belong to this profile.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Output formats available for task listings.
+///
+/// - `Table`: Human-readable fixed-width table (default)
+/// - `Json`: JSON array of task objects, suitable for scripting. An empty
+///   task list is printed as `[]` rather than a "No tasks found" message.
+/// - `Markdown`: GitHub-flavored Markdown table, for pasting into issues and
+///   wikis. Commands that don't list tasks print their usual text output.
+/// - `Tsv`: One tab-separated line per task, without a header, for `cut`,
+///   `awk` and other line-oriented tools. An empty task list prints nothing.
+///   Commands that don't list tasks print their usual text output.
+/// - `Checklist`: Markdown task list (`- [ ]` / `- [x]`), for GitHub issues.
+///   Subtasks are nested under their parent. Commands that don't list tasks
+///   print their usual text output.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Markdown,
+    Tsv,
+    Checklist,
+}
+
+/// When task tables should be colored.
+///
+/// - `Auto`: Color only when stdout is a terminal and `NO_COLOR` is not set (default)
+/// - `Always`: Always color, even when piping the output
+/// - `Never`: Never color
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Columns that task listings can be sorted by.
+///
+/// - `Position`: Manual order set with `move`, creation order until then (default)
+/// - `Id`: Task ID, i.e. creation order
+/// - `Birth`: Creation timestamp
+/// - `Done`: Completion status, pending tasks first
+/// - `Description`: Description, alphabetically and case-insensitively
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    #[default]
+    Position,
+    Id,
+    Birth,
+    Done,
+    Description,
+}
+
+/// File formats available for exporting and importing tasks.
+///
+/// - `Csv`: Comma-separated values with a header row (default)
+/// - `Json`: JSON array of task objects
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
 }
 
 /// Available commands for the todo-rs application.
 ///
 /// This enum defines all the possible actions that can be performed:
-/// - `Add`: Create a new task with a description
+/// - `Add`: Create one or more new tasks from their descriptions
 /// - `List`: Display all tasks with their status
-/// - `Remove`: Delete a task by its ID
-/// - `Done`: Mark a task as completed by its ID
+/// - `Show`: Display every field of a single task by its ID
+/// - `Search`: Display tasks whose description contains a term
+/// - `Today`: Display tasks created or due today
+/// - `History`: Show tasks completed in the last few days, grouped by day
+/// - `Stats`: Summarize how many tasks are done and pending
+/// - `Count`: Print the number of pending, done and total tasks, or just one of them
+/// - `Remove`: Delete tasks by ID or ID range, or all completed tasks with `--done`
+/// - `Undo`: Restore the most recently removed task
+/// - `Clear`: Delete all completed tasks
+/// - `Reset`: Delete every task and recreate the empty database
+/// - `Archive`: Hide a task, or every task created before a day, from listings without deleting it
+/// - `Done`: Mark one or more tasks as completed by their IDs, or choose them from a menu
+/// - `Undone`: Mark a completed task as not done by its ID
+/// - `Edit`: Update the description of an existing task, in `$EDITOR` if none is given
+/// - `Duplicate`: Add a pending copy of an existing task
+/// - `Move`: Move a task to another position in the list
+/// - `Renumber`: Rewrite task IDs to run from 1 without gaps
+/// - `Export`: Write all tasks to a file
+/// - `Import`: Add tasks read from a file
+/// - `Vacuum`: Check the database for corruption and compact the file
+/// - `Backup`: Copy the database to a timestamped backup file
+/// - `Restore`: Replace the database with a backup after validating it
+/// - `Profiles`: List the profiles that have tasks
+/// - `Completions`: Print a shell completion script (hidden from help)
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    #[command(about = "Add a new task")]
-    Add { description: String },
+    #[command(about = "Add one or more new tasks")]
+    Add {
+        #[arg(value_name = "DESCRIPTION", required = true, num_args = 1..)]
+        descriptions: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            value_parser = clap::value_parser!(u8).range(0..=3),
+            help = "Task priority (0=none, 1=low, 2=medium, 3=high)"
+        )]
+        priority: Option<u8>,
+
+        #[arg(long, value_parser = parse_date, help = "Due date in YYYY-MM-DD format")]
+        due: Option<NaiveDate>,
+
+        #[arg(
+            long = "tag",
+            value_name = "TAG",
+            value_parser = parse_tag,
+            help = "Tag to attach to the task (repeatable)"
+        )]
+        tags: Vec<String>,
+
+        #[arg(long, help = "Longer notes to keep with the task")]
+        note: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "INTERVAL",
+            value_parser = parse_recurrence,
+            help = "Add the task again when it's done: daily, weekly, monthly, or e.g. 3d, 2w, 6m"
+        )]
+        recur: Option<Recurrence>,
+
+        #[arg(
+            long,
+            value_name = "ID",
+            help = "Add the tasks as subtasks of this task"
+        )]
+        parent: Option<i64>,
+
+        #[arg(
+            long,
+            help = "Don't add a task if a pending task with the same description exists"
+        )]
+        unique: bool,
+    },
 
     #[command(about = "List all tasks")]
-    List,
+    List {
+        #[arg(long, conflicts_with = "pending", help = "Only show completed tasks")]
+        done: bool,
+
+        #[arg(long, help = "Only show tasks that are not done yet")]
+        pending: bool,
+
+        #[arg(long, value_enum, help = "Column to sort by [default: position]")]
+        sort: Option<SortKey>,
+
+        #[arg(long, help = "Reverse the sort order")]
+        reverse: bool,
+
+        #[arg(long, value_parser = parse_tag, help = "Only show tasks with this tag")]
+        tag: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "DATE",
+            value_parser = parse_date,
+            help = "Only show tasks created on or after this day (YYYY-MM-DD)"
+        )]
+        since: Option<NaiveDate>,
+
+        #[arg(
+            long,
+            value_name = "DATE",
+            value_parser = parse_date,
+            help = "Only show tasks created on or before this day (YYYY-MM-DD)"
+        )]
+        until: Option<NaiveDate>,
+
+        #[arg(
+            long,
+            value_name = "DAYS",
+            conflicts_with = "done",
+            help = "Only show pending tasks created more than DAYS days ago"
+        )]
+        older_than: Option<u32>,
+
+        #[arg(long, value_name = "N", value_parser = parse_limit, help = "Show at most N tasks")]
+        limit: Option<u32>,
+
+        #[arg(long, value_name = "N", help = "Skip the first N tasks")]
+        offset: Option<u32>,
+
+        #[arg(long, help = "Show archived tasks instead of the other ones")]
+        archived: bool,
+    },
+
+    #[command(about = "Show all details of a task by ID")]
+    Show { id: i64 },
+
+    #[command(about = "Search tasks by description")]
+    Search {
+        term: String,
+
+        #[arg(long, help = "Match approximately and rank the results by score")]
+        fuzzy: bool,
+    },
+
+    #[command(about = "Show tasks created or due today")]
+    Today,
+
+    #[command(about = "Show tasks completed in the last few days")]
+    History {
+        #[arg(
+            long,
+            default_value_t = 7,
+            value_parser = clap::value_parser!(u32).range(1..),
+            help = "Number of days to include, counting today"
+        )]
+        days: u32,
+    },
+
+    #[command(about = "Show a summary of all tasks")]
+    Stats,
+
+    #[command(about = "Count pending and completed tasks")]
+    Count {
+        #[arg(
+            long,
+            conflicts_with = "pending",
+            help = "Only print the number of completed tasks"
+        )]
+        done: bool,
+
+        #[arg(long, help = "Only print the number of tasks that are not done yet")]
+        pending: bool,
+    },
+
+    #[command(about = "Remove tasks by ID or ID range, or all completed tasks with --done")]
+    Remove {
+        #[arg(
+            value_name = "ID",
+            required_unless_present = "done",
+            value_parser = parse_id_range,
+            help = "IDs of the tasks to remove, or ranges of IDs such as 3-10"
+        )]
+        ids: Vec<RangeInclusive<i64>>,
+
+        #[arg(
+            long,
+            conflicts_with = "ids",
+            help = "Remove all completed tasks instead (asks for confirmation)"
+        )]
+        done: bool,
+    },
+
+    #[command(about = "Restore the most recently removed task")]
+    Undo,
+
+    #[command(about = "Remove all completed tasks")]
+    Clear,
+
+    #[command(about = "Delete every task in every profile (asks for confirmation)")]
+    Reset,
+
+    #[command(about = "Archive a task by ID, or old tasks with --before, hiding them from 'list'")]
+    Archive {
+        #[arg(required_unless_present = "before", conflicts_with = "before")]
+        id: Option<i64>,
+
+        #[arg(
+            long,
+            value_name = "DATE",
+            value_parser = parse_date,
+            help = "Archive every task created before this day (YYYY-MM-DD)"
+        )]
+        before: Option<NaiveDate>,
+
+        #[arg(
+            long,
+            requires = "before",
+            conflicts_with = "id",
+            help = "With --before, only archive completed tasks"
+        )]
+        done: bool,
+    },
+
+    #[command(about = "Mark one or more tasks as 'done' by ID, or pick them interactively")]
+    Done {
+        #[arg(help = "IDs of the tasks to complete; pick from a menu if omitted")]
+        ids: Vec<i64>,
+    },
+
+    #[command(about = "Mark a 'done' task as not done by ID")]
+    Undone { id: i64 },
+
+    #[command(about = "Edit a task's description by ID (opens $EDITOR if omitted)")]
+    Edit {
+        id: i64,
+        description: Option<String>,
+
+        #[arg(long, help = "Replace the task's notes (an empty note removes them)")]
+        note: Option<String>,
+    },
+
+    #[command(about = "Add a pending copy of a task by ID, with its priority, tags and notes")]
+    Duplicate { id: i64 },
+
+    #[command(about = "Move a task to another position in the list")]
+    Move {
+        id: i64,
+
+        #[arg(
+            value_parser = clap::value_parser!(i64).range(1..),
+            help = "New position, counting from 1 at the top"
+        )]
+        position: i64,
+    },
+
+    #[command(about = "Renumber task IDs from 1 without gaps (asks for confirmation)")]
+    Renumber,
+
+    #[command(about = "Export all tasks to a file")]
+    Export {
+        path: PathBuf,
+
+        #[arg(
+            id = "export_format",
+            short = 't',
+            long = "type",
+            value_enum,
+            default_value_t = ExportFormat::Csv,
+            help = "File format to write"
+        )]
+        format: ExportFormat,
+    },
+
+    #[command(about = "Import tasks from a file")]
+    Import {
+        path: PathBuf,
+
+        #[arg(
+            id = "import_format",
+            short = 't',
+            long = "type",
+            value_enum,
+            default_value_t = ExportFormat::Csv,
+            help = "File format to read"
+        )]
+        format: ExportFormat,
+    },
+
+    #[command(about = "Check the database for corruption and compact it")]
+    Vacuum,
+
+    #[command(about = "Back up the database to a file")]
+    Backup {
+        #[arg(help = "Backup file or directory [default: next to the database]")]
+        dest: Option<PathBuf>,
+    },
+
+    #[command(about = "Replace the database with a backup (asks for confirmation)")]
+    Restore { src: PathBuf },
 
-    #[command(about = "Remove a task by ID")]
-    Remove { id: i64 },
+    #[command(about = "List the profiles that have tasks")]
+    Profiles,
 
-    #[command(about = "Mark a task as 'done' by ID")]
-    Done { id: i64 },
+    #[command(hide = true, about = "Print a shell completion script to stdout")]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
-/// Parse command line arguments and return the command to execute.
+/// Parse command line arguments and return the parsed CLI.
 ///
 /// This function uses clap to parse the command line arguments and returns
-/// the specific command that should be executed. If parsing fails (due to
-/// invalid arguments or help/version requests), clap will handle the output
-/// and exit the program automatically.
+/// the command that should be executed together with the global options.
+/// If parsing fails (due to invalid arguments or help/version requests),
+/// clap will handle the output and exit the program automatically.
 ///
 /// # Returns
 ///
-/// Returns the parsed `Commands` enum variant representing the action to perform.
+/// Returns the parsed `Cli` struct holding the command and global options.
 ///
 /// # Examples
 ///
 /// ```
 /// // This would be called automatically from main()
-/// let command = parse_args();
-/// match command {
-///     Commands::Add { description } => { /* handle add */ },
-///     Commands::List => { /* handle list */ },
+/// let cli = parse_args();
+/// match cli.command {
+///     Commands::Add { .. } => { /* handle add */ },
+///     Commands::List { .. } => { /* handle list */ },
 ///     // ... other commands
 /// }
 /// ```
-pub fn parse_args() -> Commands {
-    let cli = Cli::parse();
-    cli.command
+pub fn parse_args() -> Cli {
+    Cli::parse()
+}
+
+/// Print a completion script for the given shell to stdout.
+///
+/// The script is generated from the same clap definition used for parsing,
+/// so it always covers every subcommand and option.
+///
+/// # Arguments
+///
+/// * `shell` - The shell to generate the script for
+///
+/// # Examples
+///
+/// ```
+/// print_completions(Shell::Bash);
+/// ```
+pub fn print_completions(shell: Shell) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+/// Parse a calendar date given on the command line.
+///
+/// Used as a clap value parser so malformed dates are rejected with a clear
+/// message before any database work happens.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument, expected in `YYYY-MM-DD` format
+///
+/// # Returns
+///
+/// Returns the parsed `NaiveDate`.
+///
+/// # Errors
+///
+/// Returns a descriptive message if the value is not a valid date.
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, crate::models::DATE_FORMAT)
+        .map_err(|e| format!("invalid date '{}' ({}), expected YYYY-MM-DD", value, e))
+}
+
+/// Parse the `--limit` option of `list`.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument
+///
+/// # Returns
+///
+/// Returns the limit as a positive number.
+///
+/// # Errors
+///
+/// Returns a descriptive message if the value is not a positive whole number.
+fn parse_limit(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(limit) if limit > 0 => Ok(limit),
+        _ => Err(format!(
+            "invalid limit '{}', expected a positive whole number",
+            value
+        )),
+    }
+}
+
+/// Parse a task ID or an inclusive range of IDs given to `remove`.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument, e.g. `7` or `3-10`
+///
+/// # Returns
+///
+/// Returns the range of IDs, a single-ID range for a plain ID.
+///
+/// # Errors
+///
+/// Returns a descriptive message if the value is neither an ID nor a range
+/// whose start is not after its end.
+fn parse_id_range(value: &str) -> Result<RangeInclusive<i64>, String> {
+    let invalid = || {
+        format!(
+            "invalid ID '{}', expected a task ID or a range such as 3-10",
+            value
+        )
+    };
+
+    match value.split_once('-') {
+        Some((start, end)) if !start.is_empty() => {
+            let start = start.trim().parse::<i64>().map_err(|_| invalid())?;
+            let end = end.trim().parse::<i64>().map_err(|_| invalid())?;
+            if start > end {
+                return Err(format!(
+                    "invalid range '{}', the first ID is larger than the last",
+                    value
+                ));
+            }
+            Ok(start..=end)
+        }
+        _ => {
+            let id = value.trim().parse::<i64>().map_err(|_| invalid())?;
+            Ok(id..=id)
+        }
+    }
+}
+
+/// Parse the `--recur` option of `add`.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument, e.g. `weekly` or `10d`
+///
+/// # Returns
+///
+/// Returns the parsed recurrence interval.
+///
+/// # Errors
+///
+/// Returns an error message if the value is not a known interval.
+fn parse_recurrence(value: &str) -> Result<Recurrence, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Parse the `--profile` option.
+///
+/// Surrounding whitespace is trimmed, so ` work ` and `work` name the same
+/// profile.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument
+///
+/// # Returns
+///
+/// Returns the trimmed profile name.
+///
+/// # Errors
+///
+/// Returns an error message if the name is empty.
+fn parse_profile(value: &str) -> Result<String, String> {
+    let profile = value.trim();
+    if profile.is_empty() {
+        return Err("profile name must not be empty".to_string());
+    }
+    Ok(profile.to_string())
+}
+
+/// Parse the `--date-format` option.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument, a chrono `strftime`-style format string
+///
+/// # Returns
+///
+/// Returns the format string unchanged.
+///
+/// # Errors
+///
+/// Returns an error message if the format contains an unknown or incomplete
+/// specifier, which would otherwise only show up as broken table cells.
+fn parse_date_format(value: &str) -> Result<String, String> {
+    crate::models::validate_date_format(value).map_err(|e| e.to_string())?;
+    Ok(value.to_string())
+}
+
+/// Parse a tag name given on the command line.
+///
+/// Surrounding whitespace is trimmed. Tags are stored joined with commas,
+/// so a tag must not be empty or contain a comma.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument
+///
+/// # Returns
+///
+/// Returns the trimmed tag name.
+///
+/// # Errors
+///
+/// Returns a descriptive message if the tag is empty or contains a comma.
+fn parse_tag(value: &str) -> Result<String, String> {
+    crate::models::normalize_tag(value).map_err(|e| e.to_string())
 }
@@ -1,24 +1,78 @@
 //! Configuration and directory management module.
 //!
 //! This module handles all configuration directory operations, including:
-//! - Finding the appropriate config directory (XDG compliant)
+//! - Finding the appropriate config directory (XDG compliant on Linux,
+//!   platform native on macOS and Windows)
 //! - Creating the application's config directory
 //! - Validating directory permissions
+//! - Loading user preferences from `config.toml`
 //!
-//! The module follows the XDG Base Directory specification when available,
-//! falling back to the traditional ~/.config directory structure.
+//! On Linux the module follows the XDG Base Directory specification when
+//! available, falling back to the traditional ~/.config directory structure.
+//! macOS and Windows use their native locations via the `dirs` crate.
 
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use serde::Deserialize;
 
-/// Get the XDG config directory or fall back to ~/.config.
+use crate::args::{ColorChoice, OutputFormat, SortKey};
+
+/// Name of the configuration file inside the application config directory.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// User preferences read from `config.toml`.
+///
+/// Every key is optional; an unset key falls back to the built-in default,
+/// and a command line flag always wins over the file. Unknown keys are
+/// rejected so typos don't go unnoticed.
+///
+/// # Fields
 ///
-/// This function implements the XDG Base Directory specification by first
-/// checking the `XDG_CONFIG_HOME` environment variable. If not set, it
-/// falls back to `~/.config` as the default config directory.
+/// * `date_format` - chrono format string used to display timestamps in task tables
+/// * `format` - Default for `--format` (`table`, `json`, `markdown`, `tsv` or `checklist`)
+/// * `color` - Default for `--color` (`auto`, `always` or `never`)
+/// * `default_sort` - Default for `list --sort` (`position`, `id`, `birth`, `done` or `description`)
+/// * `utc_timestamps` - Store new timestamps in UTC rather than local time (default `true`)
+/// * `migration_backups` - Number of backups taken before schema upgrades to
+///   keep (default 3, 0 turns them off)
+/// * `max_description_length` - Longest description `add` and `edit` accept,
+///   in characters (default 1000, 0 turns the limit off)
+///
+/// # Examples
+///
+/// ```toml
+/// date_format = "%d/%m/%Y %H:%M"
+/// format = "json"
+/// color = "never"
+/// default_sort = "birth"
+/// utc_timestamps = false
+/// migration_backups = 5
+/// max_description_length = 200
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub date_format: Option<String>,
+    pub format: Option<OutputFormat>,
+    pub color: Option<ColorChoice>,
+    pub default_sort: Option<SortKey>,
+    pub utc_timestamps: Option<bool>,
+    pub migration_backups: Option<usize>,
+    pub max_description_length: Option<usize>,
+}
+
+/// Get the platform's user config directory.
+///
+/// On Linux and other Unix-like systems this implements the XDG Base
+/// Directory specification by first checking the `XDG_CONFIG_HOME`
+/// environment variable, falling back to `~/.config`. As the specification
+/// requires, an empty or relative `XDG_CONFIG_HOME` is ignored. On macOS it returns
+/// `~/Library/Application Support` and on Windows `%APPDATA%`, as reported
+/// by the `dirs` crate.
 ///
 /// # Returns
 ///
@@ -26,7 +80,7 @@ use anyhow::{Context, Result};
 ///
 /// # Errors
 ///
-/// Returns an error if the home directory cannot be determined.
+/// Returns an error if the config directory cannot be determined.
 ///
 /// # Examples
 ///
@@ -35,11 +89,19 @@ use anyhow::{Context, Result};
 /// println!("Config directory: {}", config_dir.display());
 /// ```
 pub fn get_config_dir() -> Result<PathBuf> {
-    if let Ok(xdg_config) = env::var("XDG_CONFIG_HOME") {
-        Ok(PathBuf::from(xdg_config))
-    } else {
-        let home = get_home_dir()?;
-        Ok(PathBuf::from(home).join(".config"))
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        if let Some(xdg_config) = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+            if xdg_config.is_absolute() {
+                return Ok(xdg_config);
+            }
+        }
+        Ok(get_home_dir()?.join(".config"))
+    }
+
+    #[cfg(any(windows, target_os = "macos"))]
+    {
+        dirs::config_dir().context("Failed to determine the user config directory")
     }
 }
 
@@ -60,7 +122,9 @@ pub fn get_config_dir() -> Result<PathBuf> {
 ///
 /// ```
 /// let app_config = get_app_config_dir()?;
-/// // Typically: ~/.config/todo-rs or $XDG_CONFIG_HOME/todo-rs
+/// // Linux: ~/.config/todo-rs or $XDG_CONFIG_HOME/todo-rs
+/// // macOS: ~/Library/Application Support/todo-rs
+/// // Windows: %APPDATA%\todo-rs
 /// ```
 pub fn get_app_config_dir() -> Result<PathBuf> {
     Ok(get_config_dir()?.join("todo-rs"))
@@ -68,22 +132,23 @@ pub fn get_app_config_dir() -> Result<PathBuf> {
 
 /// Get the user's home directory.
 ///
-/// This function retrieves the user's home directory from the `HOME`
-/// environment variable, which is standard on Unix-like systems.
+/// This function retrieves the user's home directory using the `dirs` crate,
+/// which reads `HOME` on Unix-like systems and the user profile on Windows.
 ///
 /// # Returns
 ///
-/// Returns the home directory path as a `String`.
+/// Returns the home directory path as a `PathBuf`.
 ///
 /// # Errors
 ///
-/// Returns an error if the `HOME` environment variable is not set.
+/// Returns an error if the home directory cannot be determined.
 ///
 /// # Note
 ///
 /// This function is primarily used internally by other config functions.
-pub fn get_home_dir() -> Result<String> {
-    env::var("HOME").context("Failed to get user home directory. HOME environment variable not set")
+#[cfg_attr(any(windows, target_os = "macos"), allow(dead_code))]
+pub fn get_home_dir() -> Result<PathBuf> {
+    dirs::home_dir().context("Failed to get user home directory. HOME environment variable not set")
 }
 
 /// Create the application config directory if it doesn't exist.
@@ -92,6 +157,10 @@ pub fn get_home_dir() -> Result<String> {
 /// ready for use. It creates the directory (and any parent directories)
 /// if they don't already exist.
 ///
+/// # Arguments
+///
+/// * `quiet` - Don't print a message when the directory is created
+///
 /// # Returns
 ///
 /// Returns `Ok(())` if the directory exists or was created successfully.
@@ -105,15 +174,15 @@ pub fn get_home_dir() -> Result<String> {
 /// # Side Effects
 ///
 /// - Creates directory structure if it doesn't exist
-/// - Prints a message when creating the directory
+/// - Prints a message when creating the directory, unless `quiet` is set
 ///
 /// # Examples
 ///
 /// ```
 /// // Ensure config directory exists before using it
-/// ensure_config_dir()?;
+/// ensure_config_dir(false)?;
 /// ```
-pub fn ensure_config_dir() -> Result<()> {
+pub fn ensure_config_dir(quiet: bool) -> Result<()> {
     let config_dir = get_app_config_dir()?;
 
     if !config_dir.exists() {
@@ -123,7 +192,9 @@ pub fn ensure_config_dir() -> Result<()> {
                 config_dir.display()
             )
         })?;
-        println!("Created config directory: {}", config_dir.display());
+        if !quiet {
+            println!("Created config directory: {}", config_dir.display());
+        }
     }
 
     Ok(())
@@ -167,3 +238,311 @@ pub fn check_config_dir_writable() -> Result<()> {
 
     Ok(())
 }
+
+/// Load user preferences from the config file.
+///
+/// Reads `config.toml` from the application config directory. A missing
+/// file, or a config directory that can't be determined, yields the
+/// built-in defaults.
+///
+/// # Arguments
+///
+/// * `verbose` - Print which config file is read, if any, to stderr
+///
+/// # Returns
+///
+/// Returns the parsed `Settings`.
+///
+/// # Errors
+///
+/// Returns an error naming the file if it cannot be read, is not valid
+/// TOML, contains unknown keys or values, or has an invalid `date_format`.
+///
+/// # Examples
+///
+/// ```
+/// let settings = load_config(false)?;
+/// let color = cli.color.or(settings.color).unwrap_or(ColorChoice::Auto);
+/// ```
+pub fn load_config(verbose: bool) -> Result<Settings> {
+    let Ok(config_dir) = get_app_config_dir() else {
+        if verbose {
+            eprintln!("No config directory found, using the default settings");
+        }
+        return Ok(Settings::default());
+    };
+    read_config(&config_dir.join(CONFIG_FILE_NAME), verbose)
+}
+
+/// Read settings from a config file, or the defaults if it doesn't exist.
+///
+/// This holds the logic of `load_config`, which calls it with the file in
+/// the application config directory.
+///
+/// # Arguments
+///
+/// * `path` - Path to the config file
+/// * `verbose` - Print which config file is read, if any, to stderr
+///
+/// # Returns
+///
+/// Returns the parsed `Settings`.
+///
+/// # Errors
+///
+/// Returns an error naming the file if it cannot be read, is not valid
+/// TOML, contains unknown keys or values, or has an invalid `date_format`.
+fn read_config(path: &Path, verbose: bool) -> Result<Settings> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => {
+            if verbose {
+                eprintln!("Reading config file {}", path.display());
+            }
+            contents
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if verbose {
+                eprintln!(
+                    "No config file at {}, using the default settings",
+                    path.display()
+                );
+            }
+            return Ok(Settings::default());
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read config file {}", path.display()))
+        }
+    };
+
+    let settings: Settings = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+
+    if let Some(format) = &settings.date_format {
+        crate::models::validate_date_format(format)
+            .with_context(|| format!("Invalid date_format in config file {}", path.display()))?;
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Mutex, PoisonError};
+    use tempfile::TempDir;
+
+    use crate::args::{Cli, Commands};
+
+    /// Serializes tests that change environment variables, which are shared
+    /// by every test thread.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Run `f` with environment variables set (`Some`) or removed (`None`).
+    ///
+    /// The previous values are restored afterwards, even if `f` panics.
+    pub(crate) fn with_env<T>(vars: &[(&str, Option<&str>)], f: impl FnOnce() -> T) -> T {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let saved: Vec<_> = vars
+            .iter()
+            .map(|&(name, _)| (name, env::var_os(name)))
+            .collect();
+        for &(name, value) in vars {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+
+        for (name, value) in saved {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
+    /// Write `contents` to a config file in a new temporary directory.
+    fn config_file(contents: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    const CONFIG: &str = r#"
+        format = "json"
+        color = "always"
+        default_sort = "birth"
+        date_format = "%d/%m/%Y"
+    "#;
+
+    /// Resolve the format, color, date format and list sort the way `main`
+    /// and `handle_db_operations` combine flags with the config file.
+    fn resolve(args: &[&str], settings: &Settings) -> (OutputFormat, ColorChoice, String, SortKey) {
+        let cli = Cli::try_parse_from(args).unwrap();
+        let Commands::List { sort, .. } = cli.command else {
+            panic!("expected a list command");
+        };
+        (
+            cli.format.or(settings.format).unwrap_or_default(),
+            cli.color.or(settings.color).unwrap_or(ColorChoice::Auto),
+            cli.date_format
+                .or_else(|| settings.date_format.clone())
+                .unwrap_or_else(|| crate::models::TIMESTAMP_FORMAT.to_string()),
+            sort.or(settings.default_sort).unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn read_config_parses_the_settings() {
+        let (_dir, path) = config_file(CONFIG);
+
+        let settings = read_config(&path, false).unwrap();
+
+        assert_eq!(settings.format, Some(OutputFormat::Json));
+        assert_eq!(settings.color, Some(ColorChoice::Always));
+        assert_eq!(settings.default_sort, Some(SortKey::Birth));
+        assert_eq!(settings.date_format.as_deref(), Some("%d/%m/%Y"));
+    }
+
+    #[test]
+    fn read_config_without_a_file_uses_the_defaults() {
+        let dir = TempDir::new().unwrap();
+
+        let settings = read_config(&dir.path().join(CONFIG_FILE_NAME), false).unwrap();
+
+        assert!(settings.format.is_none());
+        assert!(settings.color.is_none());
+        assert!(settings.default_sort.is_none());
+    }
+
+    #[test]
+    fn read_config_rejects_unknown_keys() {
+        let (_dir, path) = config_file("colour = \"always\"\n");
+
+        let err = read_config(&path, false).unwrap_err();
+
+        assert!(err.to_string().starts_with("Failed to parse config file"));
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let (_dir, path) = config_file(CONFIG);
+        let settings = read_config(&path, false).unwrap();
+
+        let args = [
+            "todo-rs",
+            "--format",
+            "table",
+            "--color",
+            "never",
+            "--date-format",
+            "%Y",
+            "list",
+            "--sort",
+            "id",
+        ];
+        assert_eq!(
+            resolve(&args, &settings),
+            (
+                OutputFormat::Table,
+                ColorChoice::Never,
+                "%Y".to_string(),
+                SortKey::Id
+            )
+        );
+    }
+
+    #[test]
+    fn config_file_overrides_the_built_in_defaults() {
+        let (_dir, path) = config_file(CONFIG);
+        let settings = read_config(&path, false).unwrap();
+
+        assert_eq!(
+            resolve(&["todo-rs", "list"], &settings),
+            (
+                OutputFormat::Json,
+                ColorChoice::Always,
+                "%d/%m/%Y".to_string(),
+                SortKey::Birth
+            )
+        );
+        assert_eq!(
+            resolve(&["todo-rs", "list"], &Settings::default()),
+            (
+                OutputFormat::Table,
+                ColorChoice::Auto,
+                crate::models::TIMESTAMP_FORMAT.to_string(),
+                SortKey::default()
+            )
+        );
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    #[test]
+    fn config_dir_uses_an_absolute_xdg_config_home() {
+        let vars = [
+            ("XDG_CONFIG_HOME", Some("/xdg/config")),
+            ("HOME", Some("/home/me")),
+        ];
+
+        with_env(&vars, || {
+            assert_eq!(get_config_dir().unwrap(), PathBuf::from("/xdg/config"));
+            assert_eq!(
+                get_app_config_dir().unwrap(),
+                PathBuf::from("/xdg/config/todo-rs")
+            );
+        });
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    #[test]
+    fn config_dir_falls_back_to_home_without_a_usable_xdg_config_home() {
+        // Unset, empty and relative values are all ignored
+        for xdg in [None, Some(""), Some("relative/config")] {
+            let vars = [("XDG_CONFIG_HOME", xdg), ("HOME", Some("/home/me"))];
+
+            with_env(&vars, || {
+                assert_eq!(get_config_dir().unwrap(), PathBuf::from("/home/me/.config"));
+                assert_eq!(
+                    get_app_config_dir().unwrap(),
+                    PathBuf::from("/home/me/.config/todo-rs")
+                );
+            });
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn config_dir_uses_application_support_on_macos() {
+        let vars = [
+            ("XDG_CONFIG_HOME", Some("/xdg/config")),
+            ("HOME", Some("/Users/me")),
+        ];
+
+        with_env(&vars, || {
+            assert_eq!(
+                get_app_config_dir().unwrap(),
+                PathBuf::from("/Users/me/Library/Application Support/todo-rs")
+            );
+        });
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn config_dir_uses_appdata_on_windows() {
+        // dirs asks Windows for the roaming AppData folder, which APPDATA names
+        let vars = [("XDG_CONFIG_HOME", Some("C:\\xdg\\config"))];
+
+        with_env(&vars, || {
+            let appdata = PathBuf::from(env::var_os("APPDATA").unwrap());
+            assert_eq!(get_app_config_dir().unwrap(), appdata.join("todo-rs"));
+        });
+    }
+}
@@ -9,14 +9,27 @@
 //! The module acts as a bridge between the CLI commands and the database
 //! operations, handling all the necessary setup and error handling.
 
+use std::fmt;
 use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use rusqlite::Connection;
+use anyhow::{bail, Context, Result};
+use chrono::{Days, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::trace::{TraceEvent, TraceEventCodes};
+use rusqlite::{Connection, ErrorCode, OpenFlags, Transaction, MAIN_DB};
+use serde::Serialize;
 
-use crate::args::Commands;
-use crate::models::Task;
+use crate::args::{Commands, ExportFormat, OutputFormat};
+use crate::config::Settings;
+use crate::io_utils::{self, TableOptions};
+use crate::models::{
+    normalize_description, CompletionDay, ListOptions, Task, DEFAULT_MAX_DESCRIPTION_LENGTH,
+};
+
+/// Environment variable that overrides the default database location.
+pub const DB_PATH_ENV: &str = "TODO_RS_DB";
 
 /// SQL query to create the tasks table.
 ///
@@ -25,47 +38,530 @@ use crate::models::Task;
 /// - `description`: Task description, cannot be null
 /// - `done`: Boolean completion status, defaults to false
 /// - `birth`: Creation timestamp as text
+/// - `priority`: Priority level (0=none, 1=low, 2=medium, 3=high), defaults to 0
+/// - `due`: Optional due date as `YYYY-MM-DD` text
+/// - `done_at`: Completion timestamp as text, NULL while the task is pending
+///
+/// Later schema versions add the `notes`, `position`, `recur`, `list_name`,
+/// `parent_id`, `archived` and `recurred_from` columns.
 pub const CREATE_TASK_TABLE: &str = "CREATE TABLE IF NOT EXISTS tasks (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     description TEXT NOT NULL,
     done BOOLEAN NOT NULL DEFAULT 0,
-    birth TEXT NOT NULL
+    birth TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    due TEXT,
+    done_at TEXT
+)";
+
+/// SQL query to create the task tags table.
+///
+/// Each row attaches one tag to one task. Tag rows are removed together with
+/// their task, which requires foreign key enforcement on the connection.
+/// - `task_id`: ID of the tagged task
+/// - `tag`: Tag name, never containing a comma
+pub const CREATE_TASK_TAGS_TABLE: &str = "CREATE TABLE IF NOT EXISTS task_tags (
+    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (task_id, tag)
+)";
+
+/// SQL query to index tasks by completion status.
+///
+/// Speeds up the `--done`/`--pending` filters, `count` and `clear` on large
+/// task lists.
+pub const CREATE_DONE_INDEX: &str = "CREATE INDEX IF NOT EXISTS idx_tasks_done ON tasks(done)";
+
+/// SQL query to create the undo log table.
+///
+/// Holds copies of removed tasks so `undo` can restore them. The log is
+/// capped at `UNDO_LOG_LIMIT` entries.
+/// - `id`: Entry ID, increasing with each removal
+/// - `description`, `done`, `birth`, `priority`, `due`, `done_at`: The
+///   removed task's columns
+/// - `tags`: The removed task's tags joined with commas, NULL when it had none
+pub const CREATE_UNDO_LOG_TABLE: &str = "CREATE TABLE IF NOT EXISTS undo_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description TEXT NOT NULL,
+    done BOOLEAN NOT NULL,
+    birth TEXT NOT NULL,
+    priority INTEGER NOT NULL,
+    due TEXT,
+    done_at TEXT,
+    tags TEXT
 )";
 
+/// SQL query to add the `notes` column to the tasks table.
+pub const ADD_NOTES_COLUMN: &str = "ALTER TABLE tasks ADD COLUMN notes TEXT";
+
+/// SQL query to add the `notes` column to the undo log table.
+pub const ADD_UNDO_NOTES_COLUMN: &str = "ALTER TABLE undo_log ADD COLUMN notes TEXT";
+
+/// SQL query to add the `position` column used for manual ordering.
+pub const ADD_POSITION_COLUMN: &str = "ALTER TABLE tasks ADD COLUMN position INTEGER";
+
+/// SQL query to give existing tasks their initial position.
+///
+/// Tasks start out in creation order, at the position equal to their ID.
+pub const INIT_TASK_POSITIONS: &str = "UPDATE tasks SET position = id";
+
+/// SQL query to create the trigger that positions newly inserted tasks.
+///
+/// New tasks, whether added, imported or restored by `undo`, start at the
+/// position equal to their ID and therefore at the end of the list. This
+/// never collides with an existing position: moving tasks renumbers them
+/// from 1, so no position exceeds the number of tasks, while IDs are never
+/// reused.
+pub const CREATE_POSITION_TRIGGER: &str =
+    "CREATE TRIGGER IF NOT EXISTS tasks_initial_position AFTER INSERT ON tasks
+    WHEN NEW.position IS NULL
+    BEGIN
+        UPDATE tasks SET position = NEW.id WHERE id = NEW.id;
+    END";
+
+/// SQL query to add the `recur` column to the tasks table.
+pub const ADD_RECUR_COLUMN: &str = "ALTER TABLE tasks ADD COLUMN recur TEXT";
+
+/// SQL query to add the `recur` column to the undo log table.
+pub const ADD_UNDO_RECUR_COLUMN: &str = "ALTER TABLE undo_log ADD COLUMN recur TEXT";
+
+/// SQL query to add the `list_name` column holding each task's profile.
+///
+/// Tasks created before profiles existed belong to the default profile.
+pub const ADD_LIST_NAME_COLUMN: &str =
+    "ALTER TABLE tasks ADD COLUMN list_name TEXT NOT NULL DEFAULT 'default'";
+
+/// SQL query to add the `list_name` column to the undo log table.
+pub const ADD_UNDO_LIST_NAME_COLUMN: &str =
+    "ALTER TABLE undo_log ADD COLUMN list_name TEXT NOT NULL DEFAULT 'default'";
+
+/// SQL query to index tasks by profile.
+///
+/// Every task query is scoped to a single profile.
+pub const CREATE_LIST_NAME_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS idx_tasks_list_name ON tasks(list_name)";
+
+/// SQL query to add the `parent_id` column linking subtasks to their parent.
+///
+/// Removing a parent task turns its subtasks into top-level tasks rather
+/// than removing them too, which requires foreign key enforcement on the
+/// connection.
+pub const ADD_PARENT_ID_COLUMN: &str =
+    "ALTER TABLE tasks ADD COLUMN parent_id INTEGER REFERENCES tasks(id) ON DELETE SET NULL";
+
+/// SQL query to add the `parent_id` column to the undo log table.
+pub const ADD_UNDO_PARENT_ID_COLUMN: &str = "ALTER TABLE undo_log ADD COLUMN parent_id INTEGER";
+
+/// SQL query to index tasks by parent.
+///
+/// Keeps removing a parent task fast, since its subtasks have to be found.
+pub const CREATE_PARENT_ID_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS idx_tasks_parent_id ON tasks(parent_id)";
+
+/// SQL query to add the `archived` column.
+///
+/// Existing tasks start out not archived.
+pub const ADD_ARCHIVED_COLUMN: &str =
+    "ALTER TABLE tasks ADD COLUMN archived BOOLEAN NOT NULL DEFAULT 0";
+
+/// SQL query to add the `archived` column to the undo log table.
+pub const ADD_UNDO_ARCHIVED_COLUMN: &str =
+    "ALTER TABLE undo_log ADD COLUMN archived BOOLEAN NOT NULL DEFAULT 0";
+
+/// SQL query to add the `recurred_from` column.
+///
+/// Links the next occurrence of a recurring task to the completed task it
+/// was added for, so completing that task again doesn't add another one.
+/// Removing the completed task clears the link.
+pub const ADD_RECURRED_FROM_COLUMN: &str =
+    "ALTER TABLE tasks ADD COLUMN recurred_from INTEGER REFERENCES tasks(id) ON DELETE SET NULL";
+
+/// SQL query to index tasks by the task they recur from.
+///
+/// Keeps completing recurring tasks and removing completed ones fast.
+pub const CREATE_RECURRED_FROM_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS idx_tasks_recurred_from ON tasks(recurred_from)";
+
+/// Number of removed tasks kept in the undo log.
+pub const UNDO_LOG_LIMIT: i64 = 20;
+
+/// SQL query to add the `priority` column to databases created before it existed.
+pub const ADD_PRIORITY_COLUMN: &str =
+    "ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0";
+
+/// SQL query to add the `due` column to databases created before it existed.
+pub const ADD_DUE_COLUMN: &str = "ALTER TABLE tasks ADD COLUMN due TEXT";
+
+/// SQL query to add the `done_at` column to databases created before it existed.
+///
+/// Tasks completed before the column existed keep a NULL completion time.
+pub const ADD_DONE_AT_COLUMN: &str = "ALTER TABLE tasks ADD COLUMN done_at TEXT";
+
 /// SQL query to insert a new task.
 ///
 /// Parameters:
 /// 1. `description` - The task description
 /// 2. `birth` - The creation timestamp
-pub const INSERT_TASK: &str = "INSERT INTO tasks (description, done, birth) VALUES (?1, 0, ?2)";
+/// 3. `priority` - The task priority
+/// 4. `due` - The optional due date
+/// 5. `list_name` - The profile the task belongs to
+pub const INSERT_TASK: &str =
+    "INSERT INTO tasks (description, done, birth, priority, due, list_name)
+    VALUES (?1, 0, ?2, ?3, ?4, ?5)";
+
+/// SQL query to attach a tag to a task.
+///
+/// Tags a task already has are ignored.
+/// Parameters:
+/// 1. `task_id` - The task ID
+/// 2. `tag` - The tag name
+pub const INSERT_TASK_TAG: &str = "INSERT OR IGNORE INTO task_tags (task_id, tag) VALUES (?1, ?2)";
+
+/// SQL query to insert a task read from an import file.
+///
+/// Unlike `INSERT_TASK`, the completion status and creation timestamp are
+/// taken from the imported record. The ID is always assigned by the database.
+/// Parameters:
+/// 1. `description` - The task description
+/// 2. `done` - The completion status
+/// 3. `birth` - The creation timestamp
+/// 4. `priority` - The task priority
+/// 5. `due` - The optional due date
+/// 6. `done_at` - The optional completion timestamp
+/// 7. `notes` - The optional notes
+/// 8. `recur` - The optional recurrence interval
+/// 9. `list_name` - The profile the task is imported into
+/// 10. `archived` - Whether the task is archived
+pub const IMPORT_TASK: &str = "INSERT INTO tasks
+    (description, done, birth, priority, due, done_at, notes, recur, list_name, archived)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)";
 
 /// SQL query to select all tasks.
 ///
-/// Returns all columns for all tasks in the database.
-pub const SELECT_ALL_TASKS: &str = "SELECT id, description, done, birth FROM tasks";
+/// Returns all columns for all tasks in the database, plus the task's tags
+/// joined with commas (NULL when it has none) before the `notes`, `recur`,
+/// `parent_id` and `archived` columns. Listings append a `WHERE` clause
+/// selecting the profile, plus any filters and an `ORDER BY` clause.
+pub const SELECT_ALL_TASKS: &str = "SELECT id, description, done, birth, priority, due, done_at,
+    (SELECT GROUP_CONCAT(tag, ',') FROM task_tags WHERE task_id = tasks.id), notes, recur,
+    parent_id, archived
+    FROM tasks";
+
+/// SQL query to select a single task by ID.
+///
+/// Parameters:
+/// 1. `id` - The task ID to fetch
+/// 2. `list_name` - The profile the task must belong to
+pub const SELECT_TASK: &str = "SELECT id, description, done, birth, priority, due, done_at,
+    (SELECT GROUP_CONCAT(tag, ',') FROM task_tags WHERE task_id = tasks.id), notes, recur,
+    parent_id, archived
+    FROM tasks WHERE id = ?1 AND list_name = ?2";
+
+/// SQL query to find pending tasks with exactly the given description.
+///
+/// Archived tasks are left out, since they no longer show up in listings.
+/// Parameters:
+/// 1. `description` - The description to look for
+/// 2. `list_name` - The profile to search
+pub const FIND_PENDING_BY_DESCRIPTION: &str = "SELECT id, description, done, birth, priority, due,
+    done_at, (SELECT GROUP_CONCAT(tag, ',') FROM task_tags WHERE task_id = tasks.id), notes,
+    recur, parent_id, archived
+    FROM tasks WHERE description = ?1 AND list_name = ?2 AND done = 0 AND archived = 0
+    ORDER BY id";
+
+/// SQL query to search tasks by description.
+///
+/// `LIKE` is case-insensitive for ASCII characters, and `\` is used as the
+/// escape character so literal `%` and `_` can be matched.
+/// Parameters:
+/// 1. `pattern` - A `LIKE` pattern, e.g. `%invoice%`
+/// 2. `list_name` - The profile to search
+pub const SEARCH_TASKS: &str = "SELECT id, description, done, birth, priority, due, done_at,
+    (SELECT GROUP_CONCAT(tag, ',') FROM task_tags WHERE task_id = tasks.id), notes, recur,
+    parent_id, archived
+    FROM tasks WHERE list_name = ?2 AND description LIKE ?1 ESCAPE '\\'";
+
+/// SQL query to select tasks created or due on a given day.
+///
+/// Creation times are compared as local time against a half-open range, so
+/// timestamps stored in UTC or with a different format still match.
+/// Parameters:
+/// 1. `start` - The start of the day, in `TIMESTAMP_FORMAT`
+/// 2. `end` - The start of the next day, in `TIMESTAMP_FORMAT`
+/// 3. `date` - The day, in `DATE_FORMAT`, matched against `due`
+/// 4. `list_name` - The profile to list
+pub const SELECT_TASKS_FOR_DAY: &str =
+    "SELECT id, description, done, birth, priority, due, done_at,
+    (SELECT GROUP_CONCAT(tag, ',') FROM task_tags WHERE task_id = tasks.id), notes, recur,
+    parent_id, archived
+    FROM tasks WHERE list_name = ?4 AND (due = ?3 OR
+    (CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END >= ?1 AND
+    CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END < ?2))
+    ORDER BY id ASC";
+
+/// SQL query to select tasks completed within a time range.
+///
+/// Both bounds are inclusive local times in `TIMESTAMP_FORMAT`. Completion
+/// times stored in UTC are converted to local time before comparing, so
+/// both storage formats compare chronologically. Tasks completed before
+/// `done_at` existed have no completion time and are never returned.
+/// Results are ordered by completion time.
+/// Parameters:
+/// 1. `start` - The earliest completion timestamp
+/// 2. `end` - The latest completion timestamp
+/// 3. `list_name` - The profile to list
+pub const SELECT_COMPLETED_BETWEEN: &str =
+    "SELECT id, description, done, birth, priority, due, done_at,
+    (SELECT GROUP_CONCAT(tag, ',') FROM task_tags WHERE task_id = tasks.id), notes, recur,
+    parent_id, archived
+    FROM tasks WHERE list_name = ?3 AND done = 1 AND
+    CASE WHEN done_at LIKE '%Z' THEN datetime(done_at, 'localtime') ELSE done_at END
+    BETWEEN ?1 AND ?2
+    ORDER BY done_at ASC, id ASC";
+
+/// SQL query to count all tasks and completed tasks.
+///
+/// Returns a single row with the total count and the completed count.
+/// Parameters:
+/// 1. `list_name` - The profile to count
+pub const COUNT_TASKS: &str =
+    "SELECT COUNT(*), COALESCE(SUM(done), 0) FROM tasks WHERE list_name = ?1";
+
+/// SQL query for the creation-time figures shown by `stats`.
+///
+/// Creation times are compared as local time, whichever way they were
+/// stored. Returns a single row with the creation time of the oldest
+/// pending task in `TIMESTAMP_FORMAT` (NULL when nothing is pending) and
+/// the number of tasks created since the given time.
+/// Parameters:
+/// 1. `since` - The earliest creation time to count, in `TIMESTAMP_FORMAT`
+/// 2. `list_name` - The profile to summarize
+pub const TASK_AGE_STATS: &str = "SELECT MIN(CASE WHEN done = 0 THEN birth END),
+    COALESCE(SUM(birth >= ?1), 0)
+    FROM (SELECT done,
+    CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END AS birth
+    FROM tasks WHERE list_name = ?2)";
+
+/// SQL query to count tasks, optionally only those with a given status.
+///
+/// Parameters:
+/// 1. `done` - The completion status to count, or NULL to count every task
+/// 2. `list_name` - The profile to count
+pub const COUNT_TASKS_WITH_STATUS: &str =
+    "SELECT COUNT(*) FROM tasks WHERE list_name = ?2 AND (?1 IS NULL OR done = ?1)";
 
 /// SQL query to delete a task by ID.
 ///
 /// Parameters:
 /// 1. `id` - The task ID to delete
-pub const DELETE_TASK: &str = "DELETE FROM tasks WHERE id = ?1";
+/// 2. `list_name` - The profile the task must belong to
+pub const DELETE_TASK: &str = "DELETE FROM tasks WHERE id = ?1 AND list_name = ?2";
+
+/// SQL query to copy a task and its tags into the undo log.
+///
+/// Parameters:
+/// 1. `id` - The task ID
+/// 2. `list_name` - The profile the task must belong to
+pub const SNAPSHOT_TASK: &str = "INSERT INTO undo_log
+    (description, done, birth, priority, due, done_at, tags, notes, recur, list_name, parent_id,
+    archived)
+    SELECT description, done, birth, priority, due, done_at,
+    (SELECT GROUP_CONCAT(tag, ',') FROM task_tags WHERE task_id = tasks.id), notes, recur,
+    list_name, parent_id, archived
+    FROM tasks WHERE id = ?1 AND list_name = ?2";
+
+/// SQL query to drop all but the newest undo log entries.
+///
+/// Parameters:
+/// 1. `limit` - The number of entries to keep
+pub const TRIM_UNDO_LOG: &str =
+    "DELETE FROM undo_log WHERE id NOT IN (SELECT id FROM undo_log ORDER BY id DESC LIMIT ?1)";
+
+/// SQL query to select the ID and tags of a profile's newest undo log entry.
+///
+/// Parameters:
+/// 1. `list_name` - The profile to undo in
+pub const SELECT_LAST_UNDO: &str =
+    "SELECT id, tags FROM undo_log WHERE list_name = ?1 ORDER BY id DESC LIMIT 1";
+
+/// SQL query to recreate a task from an undo log entry.
+///
+/// The task gets a new ID; all other columns, including its profile, are
+/// copied unchanged. A subtask whose parent has been removed since comes
+/// back as a top-level task.
+/// Parameters:
+/// 1. `id` - The undo log entry ID
+pub const RESTORE_TASK: &str = "INSERT INTO tasks
+    (description, done, birth, priority, due, done_at, notes, recur, list_name, parent_id,
+    archived)
+    SELECT description, done, birth, priority, due, done_at, notes, recur, list_name,
+    (SELECT id FROM tasks WHERE id = undo_log.parent_id), archived
+    FROM undo_log WHERE id = ?1";
+
+/// SQL query to delete an undo log entry.
+///
+/// Parameters:
+/// 1. `id` - The undo log entry ID
+pub const DELETE_UNDO_ENTRY: &str = "DELETE FROM undo_log WHERE id = ?1";
+
+/// SQL query to delete all completed tasks of a profile.
+///
+/// Parameters:
+/// 1. `list_name` - The profile to clear
+pub const DELETE_DONE_TASKS: &str = "DELETE FROM tasks WHERE done = 1 AND list_name = ?1";
 
 /// SQL query to mark a task as done.
 ///
 /// Only updates tasks that are not already completed.
 /// Parameters:
 /// 1. `id` - The task ID to mark as done
-pub const UPDATE_TASK_DONE: &str = "UPDATE tasks SET done = 1 WHERE id = ?1 AND done = 0";
+/// 2. `done_at` - The completion timestamp
+/// 3. `list_name` - The profile the task must belong to
+pub const UPDATE_TASK_DONE: &str =
+    "UPDATE tasks SET done = 1, done_at = ?2 WHERE id = ?1 AND done = 0 AND list_name = ?3";
+
+/// SQL query to mark a task as not done.
+///
+/// Only updates tasks that are currently completed, clearing their
+/// completion timestamp.
+/// Parameters:
+/// 1. `id` - The task ID to mark as not done
+/// 2. `list_name` - The profile the task must belong to
+pub const UPDATE_TASK_UNDONE: &str =
+    "UPDATE tasks SET done = 0, done_at = NULL WHERE id = ?1 AND done = 1 AND list_name = ?2";
+
+/// SQL query to update a task's description.
+///
+/// Leaves the `done` status and `birth` timestamp untouched.
+/// Parameters:
+/// 1. `id` - The task ID to edit
+/// 2. `description` - The new task description
+/// 3. `list_name` - The profile the task must belong to
+pub const UPDATE_TASK_DESCRIPTION: &str =
+    "UPDATE tasks SET description = ?2 WHERE id = ?1 AND list_name = ?3";
+
+/// SQL query to select every task ID of a profile in display order.
+///
+/// Parameters:
+/// 1. `list_name` - The profile to list
+pub const SELECT_TASK_IDS_BY_POSITION: &str =
+    "SELECT id FROM tasks WHERE list_name = ?1 ORDER BY position ASC, id ASC";
+
+/// SQL query to update a task's position.
+///
+/// Parameters:
+/// 1. `id` - The task ID
+/// 2. `position` - The new position
+pub const UPDATE_TASK_POSITION: &str = "UPDATE tasks SET position = ?2 WHERE id = ?1";
+
+/// SQL query to update a task's notes.
+///
+/// Parameters:
+/// 1. `id` - The task ID to edit
+/// 2. `notes` - The new notes, or NULL to remove them
+/// 3. `list_name` - The profile the task must belong to
+pub const UPDATE_TASK_NOTES: &str = "UPDATE tasks SET notes = ?2 WHERE id = ?1 AND list_name = ?3";
+
+/// SQL query to archive a task.
+///
+/// Only updates tasks that are not archived yet.
+/// Parameters:
+/// 1. `id` - The task ID to archive
+/// 2. `list_name` - The profile the task must belong to
+pub const ARCHIVE_TASK: &str =
+    "UPDATE tasks SET archived = 1 WHERE id = ?1 AND archived = 0 AND list_name = ?2";
+
+/// SQL query to archive every task created before a point in time.
+///
+/// Creation times are compared in local time, whichever way they were stored.
+/// Parameters:
+/// 1. `list_name` - The profile to archive tasks in
+/// 2. `before` - Local timestamp in `TIMESTAMP_FORMAT`; older tasks are archived
+/// 3. `done_only` - When true, only completed tasks are archived
+pub const ARCHIVE_TASKS_BEFORE: &str = "UPDATE tasks SET archived = 1
+    WHERE list_name = ?1 AND archived = 0 AND (?3 = 0 OR done = 1) AND
+    CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END < ?2";
+
+/// SQL query to make a task a subtask of another.
+///
+/// Parameters:
+/// 1. `id` - The task ID
+/// 2. `parent_id` - The parent task ID
+pub const UPDATE_TASK_PARENT: &str = "UPDATE tasks SET parent_id = ?2 WHERE id = ?1";
+
+/// SQL batch that renumbers task IDs to 1, 2, 3, ... in their current order.
+///
+/// Every reference to a task ID follows along: tags, subtasks' parents,
+/// the tasks next occurrences recur from and the parents remembered in the
+/// undo log. Undo log parents that no longer
+/// exist are cleared. The IDs are first made negative so no two rows share
+/// an ID midway, and foreign keys are only checked at commit, so the batch
+/// must run inside a transaction. Afterwards the `AUTOINCREMENT` counter is
+/// reset so new tasks continue from the highest ID, and positions are
+/// renumbered from 1 per profile, keeping them below every task ID as
+/// `CREATE_POSITION_TRIGGER` requires.
+pub const RENUMBER_TASKS: &str = "PRAGMA defer_foreign_keys = ON;
+    CREATE TEMP TABLE id_map AS
+        SELECT id AS old_id, ROW_NUMBER() OVER (ORDER BY id) AS new_id FROM tasks;
+    UPDATE tasks SET id = -id;
+    UPDATE tasks SET id = (SELECT new_id FROM id_map WHERE old_id = -tasks.id);
+    UPDATE tasks SET parent_id = (SELECT new_id FROM id_map WHERE old_id = tasks.parent_id)
+        WHERE parent_id IS NOT NULL;
+    UPDATE tasks SET recurred_from =
+        (SELECT new_id FROM id_map WHERE old_id = tasks.recurred_from)
+        WHERE recurred_from IS NOT NULL;
+    UPDATE task_tags SET task_id = -task_id;
+    UPDATE task_tags SET task_id = (SELECT new_id FROM id_map WHERE old_id = -task_tags.task_id);
+    UPDATE undo_log SET parent_id = (SELECT new_id FROM id_map WHERE old_id = undo_log.parent_id)
+        WHERE parent_id IS NOT NULL;
+    UPDATE sqlite_sequence SET seq = (SELECT COUNT(*) FROM tasks) WHERE name = 'tasks';
+    UPDATE tasks SET position = (SELECT rank FROM
+        (SELECT id, ROW_NUMBER() OVER (PARTITION BY list_name ORDER BY position, id) AS rank
+        FROM tasks) AS ranked WHERE ranked.id = tasks.id);
+    DROP TABLE temp.id_map;";
+
+/// SQL query to count the tasks of every profile.
+pub const COUNT_ALL_TASKS: &str = "SELECT COUNT(*) FROM tasks";
+
+/// SQL batch that drops every todo-rs table.
+///
+/// Their indexes and triggers go with them. Tags are dropped before the
+/// tasks they reference.
+pub const DROP_ALL_TABLES: &str = "DROP TABLE IF EXISTS task_tags;
+    DROP TABLE IF EXISTS undo_log;
+    DROP TABLE IF EXISTS tasks;";
+
+/// SQL query to list the profiles that have tasks.
+pub const SELECT_PROFILES: &str = "SELECT DISTINCT list_name FROM tasks ORDER BY list_name";
+
+/// SQL query to update how often a task recurs.
+///
+/// Parameters:
+/// 1. `id` - The task ID
+/// 2. `recur` - The recurrence interval, or NULL for a one-off task
+pub const UPDATE_TASK_RECUR: &str = "UPDATE tasks SET recur = ?2 WHERE id = ?1";
+
+/// SQL query to record which task a next occurrence was added for.
+///
+/// Parameters:
+/// 1. `id` - The ID of the next occurrence
+/// 2. `recurred_from` - The ID of the completed task
+pub const UPDATE_TASK_RECURRED_FROM: &str = "UPDATE tasks SET recurred_from = ?2 WHERE id = ?1";
 
-/// Get the full path to the database file.
+/// SQL query to check whether a task already has its next occurrence.
 ///
-/// This function combines the application config directory with the
-/// database filename to create the full path where the SQLite database
-/// should be stored.
+/// Parameters:
+/// 1. `id` - The ID of the recurring task
+pub const HAS_NEXT_OCCURRENCE: &str = "SELECT EXISTS(SELECT 1 FROM tasks WHERE recurred_from = ?1)";
+
+/// Get the path to the default database file.
+///
+/// The default database is `tasks.db` inside the application config
+/// directory. Use `custom_db_path` first to honour `--db` and `TODO_RS_DB`.
 ///
 /// # Returns
 ///
-/// Returns a `PathBuf` pointing to the database file location.
+/// Returns a `PathBuf` pointing to the default database file location.
 ///
 /// # Errors
 ///
@@ -79,150 +575,2219 @@ pub const UPDATE_TASK_DONE: &str = "UPDATE tasks SET done = 1 WHERE id = ?1 AND
 /// ```
 pub fn get_db_path() -> Result<PathBuf> {
     use crate::config;
+
     Ok(config::get_app_config_dir()?.join("tasks.db"))
 }
 
-/// Check if the database file exists.
+/// Get the user-selected database path, if any.
 ///
-/// This is a simple wrapper around `Path::exists()` to check if the
-/// database file has been created.
+/// The database location is resolved in the following order:
+/// 1. The global `--db <PATH>` flag, resolved against the current directory
+/// 2. The `TODO_RS_DB` environment variable, used verbatim when set and non-empty
+/// 3. `tasks.db` inside the application config directory (see `get_db_path`)
+///
+/// This function covers steps 1 and 2 and returns `None` when neither is
+/// given, in which case the caller falls back to the default location.
 ///
 /// # Arguments
 ///
-/// * `db_path` - Path to the database file
+/// * `flag` - The path given with `--db`, if any
 ///
 /// # Returns
 ///
-/// Returns `true` if the database file exists, `false` otherwise.
+/// Returns the custom database path, or `None` to use the default one.
+///
+/// # Errors
+///
+/// Returns an error if a relative `--db` path cannot be resolved.
 ///
 /// # Examples
 ///
 /// ```
-/// let db_path = get_db_path()?;
-/// if check_db_exists(&db_path) {
-///     println!("Database exists");
-/// } else {
-///     println!("Database needs to be created");
-/// }
+/// let db_path = match custom_db_path(cli.db.as_deref())? {
+///     Some(path) => path,
+///     None => get_db_path()?,
+/// };
 /// ```
-pub fn check_db_exists(db_path: &Path) -> bool {
-    db_path.exists()
+pub fn custom_db_path(flag: Option<&Path>) -> Result<Option<PathBuf>> {
+    if let Some(path) = flag {
+        return resolve_db_path(path).map(Some);
+    }
+
+    Ok(std::env::var_os(DB_PATH_ENV)
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from))
 }
 
-/// Create a new database file.
+/// Resolve a user-supplied database path.
 ///
-/// This function creates an empty SQLite database file at the specified path.
-/// The actual table structure is created later when the database is first opened.
+/// Relative paths are resolved against the current working directory so
+/// the resulting path stays valid regardless of later directory changes.
 ///
 /// # Arguments
 ///
-/// * `db_path` - Path where the database file should be created
+/// * `path` - The database path given on the command line
 ///
 /// # Returns
 ///
-/// Returns `Ok(())` if the database file was created successfully.
+/// Returns an absolute `PathBuf` for the database file.
 ///
 /// # Errors
 ///
-/// Returns an error if file creation fails due to permissions or other IO issues.
+/// Returns an error if the current working directory cannot be determined.
 ///
 /// # Examples
 ///
 /// ```
-/// let db_path = get_db_path()?;
-/// create_database(&db_path)?;
-/// println!("Database created at: {}", db_path.display());
+/// let db_path = resolve_db_path(Path::new("work.db"))?;
+/// // e.g. /home/user/projects/work.db
 /// ```
-pub fn create_database(db_path: &Path) -> Result<()> {
-    File::create(db_path)
-        .with_context(|| format!("Failed to create database at {}", db_path.display()))?;
-    Ok(())
+fn resolve_db_path(path: &Path) -> Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+
+    let cwd = std::env::current_dir().context("Failed to determine current directory")?;
+    Ok(cwd.join(path))
 }
 
-/// Handle database operations based on the provided command.
+/// Check if a table has a column with the given name.
 ///
-/// This is the main orchestration function that:
-/// 1. Opens a database connection
-/// 2. Ensures the tasks table exists
-/// 3. Executes the appropriate command
-/// 4. Handles all output and error reporting
+/// This is used by the first schema migration to detect databases
+/// created by older versions of the application.
+///
+/// # Arguments
+///
+/// * `conn` - SQLite database connection
+/// * `table` - Name of the table to inspect
+/// * `column` - Name of the column to look for
+///
+/// # Returns
+///
+/// Returns `true` if the column exists, `false` otherwise.
+///
+/// # Errors
+///
+/// Returns an error if the table information cannot be queried.
+///
+/// # Examples
+///
+/// ```
+/// if !column_exists(&conn, "tasks", "priority")? {
+///     conn.execute(ADD_PRIORITY_COLUMN, [])?;
+/// }
+/// ```
+pub fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    Ok(names.any(|name| name.map(|n| n == column).unwrap_or(false)))
+}
+
+/// How long a connection waits for another process's lock before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Open a database connection with the application's standard settings.
+///
+/// Every connection should be opened through this function so they all
+/// behave the same way:
+/// - Foreign keys are enforced, so deleting a task also deletes its tags
+/// - WAL journal mode lets readers and a writer work concurrently; the
+///   `-wal` and `-shm` files live next to the database file
+/// - A busy timeout makes a second invocation wait for a lock instead of
+///   failing at once with "database is locked"
 ///
 /// # Arguments
 ///
 /// * `db_path` - Path to the database file
-/// * `command` - The command to execute (Add, List, Remove, or Done)
 ///
 /// # Returns
 ///
-/// Returns `Ok(())` if the operation completed successfully.
+/// Returns the configured connection.
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - Database connection fails
-/// - Table creation fails
-/// - The specific command operation fails
+/// Returns an error if the database cannot be opened or configured.
 ///
 /// # Examples
 ///
 /// ```
-/// let db_path = get_db_path()?;
-/// let command = Commands::Add { description: "Test task".to_string() };
-/// handle_db_operations(&db_path, command)?;
+/// let conn = open_connection(&get_db_path()?)?;
 /// ```
-pub fn handle_db_operations(db_path: &Path, command: Commands) -> Result<()> {
+pub fn open_connection(db_path: &Path) -> Result<Connection> {
     let conn = Connection::open(db_path)
         .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
 
-    Task::create_default(&conn).context("Failed to create tasks table")?;
+    conn.pragma_update(None, "foreign_keys", true)
+        .context("Failed to enable foreign keys")?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .context("Failed to enable WAL mode")?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .context("Failed to set busy timeout")?;
 
-    match command {
-        Commands::Add { description } => {
-            let id = Task::add(&conn, description).context("Failed to add task")?;
-            println!("Task added successfully with id: {}", id);
-        }
-        Commands::List => {
-            let tasks = Task::list(&conn).context("Failed to list tasks")?;
+    Ok(conn)
+}
 
-            if tasks.is_empty() {
-                println!("No tasks found");
-            } else {
-                // Print header
-                println!(
-                    "{:<8} | {:<8} | {:<19} | DESCRIPTION",
-                    "ID", "DONE", "BIRTH"
-                );
-                println!("{}", "-".repeat(60));
-
-                // Print each task
-                for task in tasks {
-                    let done_display = if task.done { "true" } else { "false" };
-                    println!(
-                        "{:<8} | {:<8} | {:<19} | {}",
-                        task.id, done_display, task.birth, task.description
-                    );
-                }
-            }
+/// Check that the database file is a readable SQLite database before using it.
+///
+/// A truncated file, or one that isn't a SQLite database at all, can be
+/// opened without complaint and only fails on the first query with a
+/// low-level SQLite error. This opens the file read-only and reads the
+/// schema, which covers the file header and the first page, so such files
+/// are reported up front with advice on how to recover.
+///
+/// Every command runs this check, so it deliberately stops short of
+/// `PRAGMA quick_check`: that reads every page and would make each command
+/// slower as the database grows. The full checks are left to `vacuum`,
+/// which runs `PRAGMA integrity_check`, and to `restore`, which checks the
+/// backup with `validate_db`.
+///
+/// # Arguments
+///
+/// * `db_path` - Path to the database file
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the database passes the check.
+///
+/// # Errors
+///
+/// Returns an error telling the user to copy a backup over the file or delete it
+/// if it is not a SQLite database or is damaged, or the underlying error if
+/// it can't be read for another reason.
+///
+/// # Examples
+///
+/// ```
+/// let db_path = get_db_path()?;
+/// validate_database(&db_path)?;
+/// let conn = open_connection(&db_path)?;
+/// ```
+pub fn validate_database(db_path: &Path) -> Result<()> {
+    let corrupt = |problem: String| {
+        anyhow::anyhow!(
+            "Database file {} appears corrupt ({}); copy a backup over it, or delete it \
+             to start over",
+            db_path.display(),
+            problem
+        )
+    };
+
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .context("Failed to set busy timeout")?;
+
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    }) {
+        Ok(_) => Ok(()),
+        Err(e)
+            if matches!(
+                e.sqlite_error_code(),
+                Some(ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt)
+            ) =>
+        {
+            Err(corrupt(e.to_string()))
+        }
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to check database at {}", db_path.display()))
         }
-        Commands::Remove { id } => {
-            let removed = Task::remove(&conn, id).context("Failed to remove task")?;
+    }
+}
 
-            if removed {
-                println!("Task {} removed!", id);
-            } else {
-                println!("No task found with id: {}", id);
-            }
+/// Print every SQL statement run on a connection to stderr, with its timing.
+///
+/// Used for `--verbose`. Statements are printed once they finish, with
+/// the time SQLite spent on them.
+///
+/// # Arguments
+///
+/// * `conn` - Database connection to trace
+///
+/// # Examples
+///
+/// ```
+/// let conn = open_connection(&db_path)?;
+/// log_statements(&conn);
+/// ```
+pub fn log_statements(conn: &Connection) {
+    fn log(event: TraceEvent<'_>) {
+        if let TraceEvent::Profile(stmt, duration) = event {
+            eprintln!(
+                "[sql {:.3} ms] {}",
+                duration.as_secs_f64() * 1000.0,
+                stmt.sql()
+            );
         }
-        Commands::Done { id } => {
-            let updated = Task::mark_done(&conn, id).context("Failed to mark task as done")?;
+    }
 
-            if updated {
-                println!("Task {} marked as done!", id);
-            } else {
-                println!("Task {} already completed or doesn't exist.", id);
-            }
+    conn.trace_v2(TraceEventCodes::SQLITE_TRACE_PROFILE, Some(log));
+}
+
+/// Run a closure inside a database transaction.
+///
+/// The transaction is committed if the closure returns `Ok` and rolled back
+/// if it returns `Err`, so multi-step writes either all apply or none do.
+///
+/// # Arguments
+///
+/// * `conn` - Database connection to open the transaction on
+/// * `f` - Closure performing the writes against the transaction
+///
+/// # Returns
+///
+/// Returns the closure's value once the transaction has been committed.
+///
+/// # Errors
+///
+/// Returns an error if the transaction cannot be started or committed, or
+/// the closure's own error after rolling back.
+///
+/// # Examples
+///
+/// ```
+/// let completed = with_transaction(&mut conn, |tx| {
+///     let mut count = 0;
+///     for id in [1, 2, 3] {
+///         if Task::mark_done(tx, "default", id, false)? {
+///             count += 1;
+///         }
+///     }
+///     Ok(count)
+/// })?;
+/// ```
+pub fn with_transaction<T, F>(conn: &mut Connection, f: F) -> Result<T>
+where
+    F: FnOnce(&Transaction) -> Result<T>,
+{
+    let tx = conn.transaction().context("Failed to start transaction")?;
+
+    match f(&tx) {
+        Ok(value) => {
+            tx.commit().context("Failed to commit transaction")?;
+            Ok(value)
+        }
+        Err(e) => {
+            tx.rollback().context("Failed to roll back transaction")?;
+            Err(e)
         }
     }
+}
 
-    Ok(())
+/// A schema migration step.
+///
+/// Each step upgrades the database from one schema version to the next and
+/// runs inside the transaction opened by `run_migrations`.
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Ordered schema migrations.
+///
+/// The entry at index `N` upgrades a database from schema version `N` to
+/// `N + 1`, so the current schema version is `MIGRATIONS.len()`. New schema
+/// changes are added by appending a step; existing steps must never change.
+const MIGRATIONS: &[Migration] = &[
+    migrate_to_v1,
+    migrate_to_v2,
+    migrate_to_v3,
+    migrate_to_v4,
+    migrate_to_v5,
+    migrate_to_v6,
+    migrate_to_v7,
+    migrate_to_v8,
+    migrate_to_v9,
+    migrate_to_v10,
+];
+
+/// Bring the database schema up to date.
+///
+/// The schema version is stored in SQLite's `PRAGMA user_version`, which is
+/// 0 for new databases and for databases created before versioning existed.
+/// Every migration newer than the stored version is applied in order, each
+/// in its own transaction together with the version bump, so an interrupted
+/// upgrade never leaves the schema half-applied.
+///
+/// # Arguments
+///
+/// * `conn` - SQLite database connection
+///
+/// # Returns
+///
+/// Returns `Ok(())` once the schema is at the latest version.
+///
+/// # Errors
+///
+/// Returns an error if the database was written by a newer version of
+/// todo-rs, or if a migration fails.
+///
+/// # Examples
+///
+/// ```
+/// let mut conn = Connection::open("tasks.db")?;
+/// run_migrations(&mut conn)?;
+/// ```
+pub fn run_migrations(conn: &mut Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    if version > MIGRATIONS.len() {
+        bail!(
+            "Database schema version {} is newer than the latest supported version {}",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let to = from + 1;
+        with_transaction(conn, |tx| {
+            migration(tx)?;
+            tx.pragma_update(None, "user_version", to)?;
+            Ok(())
+        })
+        .with_context(|| format!("Failed to migrate to schema version {}", to))?;
+    }
+
+    Ok(())
+}
+
+/// Schema version 1: the tasks and task tags tables.
+///
+/// Databases created before schema versioning may already contain some of
+/// this schema, so the step only creates the tables and adds the
+/// `priority`, `due` and `done_at` columns when they are missing.
+fn migrate_to_v1(conn: &Connection) -> Result<()> {
+    conn.execute(CREATE_TASK_TABLE, [])?;
+    conn.execute(CREATE_TASK_TAGS_TABLE, [])?;
+
+    let columns = [
+        ("priority", ADD_PRIORITY_COLUMN),
+        ("due", ADD_DUE_COLUMN),
+        ("done_at", ADD_DONE_AT_COLUMN),
+    ];
+
+    for (column, sql) in columns {
+        if !column_exists(conn, "tasks", column)? {
+            conn.execute(sql, [])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Schema version 2: an index on the `done` column.
+///
+/// Existing databases get the index the next time they are opened.
+fn migrate_to_v2(conn: &Connection) -> Result<()> {
+    conn.execute(CREATE_DONE_INDEX, [])?;
+    Ok(())
+}
+
+/// Schema version 3: the undo log of removed tasks.
+fn migrate_to_v3(conn: &Connection) -> Result<()> {
+    conn.execute(CREATE_UNDO_LOG_TABLE, [])?;
+    Ok(())
+}
+
+/// Schema version 4: free-form notes on tasks.
+///
+/// The undo log gets the column too, so removed tasks keep their notes.
+fn migrate_to_v4(conn: &Connection) -> Result<()> {
+    conn.execute(ADD_NOTES_COLUMN, [])?;
+    conn.execute(ADD_UNDO_NOTES_COLUMN, [])?;
+    Ok(())
+}
+
+/// Schema version 5: a manual position for ordering task listings.
+fn migrate_to_v5(conn: &Connection) -> Result<()> {
+    conn.execute(ADD_POSITION_COLUMN, [])?;
+    conn.execute(INIT_TASK_POSITIONS, [])?;
+    conn.execute(CREATE_POSITION_TRIGGER, [])?;
+    Ok(())
+}
+
+/// Schema version 6: recurrence intervals for repeating tasks.
+///
+/// The undo log gets the column too, so removed tasks keep repeating once
+/// restored.
+fn migrate_to_v6(conn: &Connection) -> Result<()> {
+    conn.execute(ADD_RECUR_COLUMN, [])?;
+    conn.execute(ADD_UNDO_RECUR_COLUMN, [])?;
+    Ok(())
+}
+
+/// Schema version 7: profiles, separate task lists within one database.
+///
+/// Existing tasks and undo log entries are assigned to the default profile.
+fn migrate_to_v7(conn: &Connection) -> Result<()> {
+    conn.execute(ADD_LIST_NAME_COLUMN, [])?;
+    conn.execute(ADD_UNDO_LIST_NAME_COLUMN, [])?;
+    conn.execute(CREATE_LIST_NAME_INDEX, [])?;
+    Ok(())
+}
+
+/// Schema version 8: subtasks, linked to their parent task.
+///
+/// The undo log keeps the parent too, so a removed subtask is restored
+/// under its parent if that still exists.
+fn migrate_to_v8(conn: &Connection) -> Result<()> {
+    conn.execute(ADD_PARENT_ID_COLUMN, [])?;
+    conn.execute(ADD_UNDO_PARENT_ID_COLUMN, [])?;
+    conn.execute(CREATE_PARENT_ID_INDEX, [])?;
+    Ok(())
+}
+
+/// Schema version 9: archived tasks, kept but hidden from `list`.
+fn migrate_to_v9(conn: &Connection) -> Result<()> {
+    conn.execute(ADD_ARCHIVED_COLUMN, [])?;
+    conn.execute(ADD_UNDO_ARCHIVED_COLUMN, [])?;
+    Ok(())
+}
+
+/// Schema version 10: next occurrences linked to the task they recur from.
+fn migrate_to_v10(conn: &Connection) -> Result<()> {
+    conn.execute(ADD_RECURRED_FROM_COLUMN, [])?;
+    conn.execute(CREATE_RECURRED_FROM_INDEX, [])?;
+    Ok(())
+}
+
+/// Check if the database file exists.
+///
+/// This is a simple wrapper around `Path::exists()` to check if the
+/// database file has been created.
+///
+/// # Arguments
+///
+/// * `db_path` - Path to the database file
+///
+/// # Returns
+///
+/// Returns `true` if the database file exists, `false` otherwise.
+///
+/// # Examples
+///
+/// ```
+/// let db_path = get_db_path()?;
+/// if check_db_exists(&db_path) {
+///     println!("Database exists");
+/// } else {
+///     println!("Database needs to be created");
+/// }
+/// ```
+pub fn check_db_exists(db_path: &Path) -> bool {
+    db_path.exists()
+}
+
+/// Create a new database file with the current schema.
+///
+/// This function creates a SQLite database at the specified path, creating
+/// any missing parent directories first so paths from `--db` or
+/// `TODO_RS_DB` can point into directories that don't exist yet. All
+/// migrations are run straight away, so the new file is a complete database
+/// even if the program stops before the first command uses it.
+///
+/// # Arguments
+///
+/// * `db_path` - Path where the database file should be created
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the database was created and initialized successfully.
+///
+/// # Errors
+///
+/// Returns an error if the parent directories or the file cannot be created
+/// due to permissions or other IO issues, or if the schema cannot be created.
+///
+/// # Examples
+///
+/// ```
+/// let db_path = get_db_path()?;
+/// create_database(&db_path)?;
+/// println!("Database created at: {}", db_path.display());
+/// ```
+pub fn create_database(db_path: &Path) -> Result<()> {
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+
+    let mut conn = open_connection(db_path)
+        .with_context(|| format!("Failed to create database at {}", db_path.display()))?;
+    run_migrations(&mut conn)
+        .with_context(|| format!("Failed to initialize database at {}", db_path.display()))?;
+    Ok(())
+}
+
+/// Error returned when a command names a task that doesn't exist, or that
+/// isn't in the state the command needs (e.g. `done` on a completed task).
+///
+/// `main` exits with a dedicated status code for this error so scripts can
+/// tell a missing task apart from other failures.
+#[derive(Debug)]
+pub struct NoMatchingTask(pub String);
+
+impl fmt::Display for NoMatchingTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NoMatchingTask {}
+
+/// Outcome of the `vacuum` maintenance command.
+///
+/// # Fields
+///
+/// * `integrity_ok` - Whether `PRAGMA integrity_check` reported no problems
+/// * `problems` - The problems reported by the integrity check, if any
+/// * `vacuumed` - Whether `VACUUM` ran; it is skipped when the check fails
+/// * `size_before` - Size in bytes of the database and its WAL file before
+/// * `size_after` - Size in bytes of the database and its WAL file after
+#[derive(Debug, Serialize)]
+pub struct VacuumReport {
+    pub integrity_ok: bool,
+    pub problems: Vec<String>,
+    pub vacuumed: bool,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// Check the database for corruption and compact it.
+///
+/// Runs `PRAGMA integrity_check` and, if it passes, `VACUUM` to rebuild the
+/// file without the free pages left behind by removed tasks. In WAL mode the
+/// rebuilt pages land in the `-wal` file first, so the WAL is checkpointed
+/// and truncated before the size is measured again. A corrupt database is
+/// left untouched.
+///
+/// # Arguments
+///
+/// * `conn` - SQLite database connection, with no transaction open
+///
+/// # Returns
+///
+/// Returns a `VacuumReport` with the check result and the file sizes.
+///
+/// # Errors
+///
+/// Returns an error if a transaction is open on `conn`, since `VACUUM`
+/// cannot run inside one, or if any of the statements fail.
+///
+/// # Examples
+///
+/// ```
+/// let report = vacuum(&conn)?;
+/// println!("{} -> {} bytes", report.size_before, report.size_after);
+/// ```
+pub fn vacuum(conn: &Connection) -> Result<VacuumReport> {
+    if !conn.is_autocommit() {
+        bail!("Cannot vacuum the database while a transaction is open");
+    }
+
+    let size_before = database_size(conn)?;
+
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter(|line| !matches!(line.as_deref(), Ok("ok")))
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to check database integrity")?;
+    let integrity_ok = problems.is_empty();
+
+    if integrity_ok {
+        conn.execute_batch("VACUUM")
+            .context("Failed to vacuum database")?;
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")
+            .context("Failed to checkpoint the WAL file")?;
+    }
+
+    Ok(VacuumReport {
+        integrity_ok,
+        problems,
+        vacuumed: integrity_ok,
+        size_before,
+        size_after: database_size(conn)?,
+    })
+}
+
+/// Copy the live database to a backup file.
+///
+/// Uses SQLite's online backup API, so the copy is consistent even while
+/// the database is in WAL mode or being written by another process. When
+/// `dest` is not given, or names an existing directory, the backup is
+/// written there as `tasks-YYYYMMDD-HHMMSS.db`; without `dest` that is the
+/// directory holding the database, i.e. the config directory by default.
+///
+/// # Arguments
+///
+/// * `conn` - SQLite database connection to back up
+/// * `dest` - Optional backup file or directory
+///
+/// # Returns
+///
+/// Returns the path of the backup file that was written.
+///
+/// # Errors
+///
+/// Returns an error if the backup file already exists, if the database is
+/// in memory and no `dest` is given, or if the backup fails.
+///
+/// # Examples
+///
+/// ```
+/// let path = backup(&conn, None)?;
+/// println!("Backup written to {}", path.display());
+/// ```
+pub fn backup(conn: &Connection, dest: Option<&Path>) -> Result<PathBuf> {
+    let file_name = format!("tasks-{}.db", Local::now().format("%Y%m%d-%H%M%S"));
+
+    let dest = match dest {
+        Some(dest) if dest.is_dir() => dest.join(file_name),
+        Some(dest) => dest.to_path_buf(),
+        None => {
+            let db_path = conn
+                .path()
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .context("The database has no file to back up next to; pass a destination")?;
+            db_path.with_file_name(file_name)
+        }
+    };
+
+    if dest.exists() {
+        bail!("Backup file {} already exists", dest.display());
+    }
+
+    conn.backup(MAIN_DB, &dest, None)
+        .with_context(|| format!("Failed to back up database to {}", dest.display()))?;
+    Ok(dest)
+}
+
+/// Delete every task and start over with an empty database.
+///
+/// Drops all tables, including the tags and the undo log, and recreates
+/// them by running every schema migration, all in a single transaction.
+/// Tasks of every profile are deleted and IDs start again from 1.
+///
+/// # Arguments
+///
+/// * `conn` - SQLite database connection
+///
+/// # Returns
+///
+/// Returns the number of tasks that were deleted.
+///
+/// # Errors
+///
+/// Returns an error if any statement fails, in which case the database is
+/// left unchanged.
+///
+/// # Examples
+///
+/// ```
+/// let deleted = reset(&mut conn)?;
+/// println!("Deleted {} tasks", deleted);
+/// ```
+pub fn reset(conn: &mut Connection) -> Result<usize> {
+    with_transaction(conn, |tx| {
+        let count = tx.query_row(COUNT_ALL_TASKS, [], |row| row.get(0))?;
+
+        tx.execute_batch(DROP_ALL_TABLES)?;
+        for migration in MIGRATIONS {
+            migration(tx)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+
+        Ok(count)
+    })
+}
+
+/// Number of cells in the progress bar printed by `stats`.
+const STATS_BAR_WIDTH: usize = 20;
+
+/// Number of backups taken before schema upgrades that are kept by default.
+pub const DEFAULT_MIGRATION_BACKUPS: usize = 3;
+
+/// Back up the database before its schema is upgraded.
+///
+/// The backup is written next to the database as
+/// `<file name>.bak-YYYYMMDD-HHMMSS`, e.g. `tasks.db.bak-20241207-143015`,
+/// using SQLite's online backup API like `backup`. Afterwards only the
+/// `keep` most recent of these backups are kept; older ones are deleted.
+/// Backups written by the `backup` command are never touched.
+///
+/// # Arguments
+///
+/// * `conn` - SQLite database connection to back up
+/// * `db_path` - Path to the database file
+/// * `keep` - Number of backups to keep, including the new one
+///
+/// # Returns
+///
+/// Returns the path of the backup file that was written.
+///
+/// # Errors
+///
+/// Returns an error if the backup fails or old backups can't be listed or
+/// deleted.
+///
+/// # Examples
+///
+/// ```
+/// let path = backup_database(&conn, &db_path, DEFAULT_MIGRATION_BACKUPS)?;
+/// println!("Backup written to {}", path.display());
+/// ```
+pub fn backup_database(conn: &Connection, db_path: &Path, keep: usize) -> Result<PathBuf> {
+    let file_name = db_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("The database path has no file name")?;
+    let prefix = format!("{}.bak-", file_name);
+    let dest = db_path.with_file_name(format!(
+        "{}{}",
+        prefix,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    conn.backup(MAIN_DB, &dest, None)
+        .with_context(|| format!("Failed to back up database to {}", dest.display()))?;
+
+    let dir = match db_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list backups in {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let is_backup = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&prefix));
+        if is_backup {
+            backups.push(path);
+        }
+    }
+
+    // The timestamps sort chronologically, so the oldest backups come first
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        std::fs::remove_file(old)
+            .with_context(|| format!("Failed to delete old backup {}", old.display()))?;
+    }
+
+    Ok(dest)
+}
+
+/// Check whether opening the database will upgrade an existing schema.
+///
+/// A new, empty database needs migrations too, but has nothing worth
+/// backing up, so it only counts once it has a `tasks` table.
+fn needs_upgrade(conn: &Connection) -> Result<bool> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version < MIGRATIONS.len() && column_exists(conn, "tasks", "id")?)
+}
+
+/// Check that a file is a todo-rs database that can be restored.
+///
+/// The file is opened read-only and must be an intact SQLite database with
+/// a `tasks` table holding at least the original columns. Databases from
+/// older versions pass and are migrated after restoring; ones written by a
+/// newer version of todo-rs are rejected.
+///
+/// # Arguments
+///
+/// * `path` - Path to the database file to check
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the file can be restored.
+///
+/// # Errors
+///
+/// Returns an error describing the first problem found: the file is
+/// missing or not a SQLite database, fails the integrity check, has no
+/// usable `tasks` table, or uses a newer schema version.
+///
+/// # Examples
+///
+/// ```
+/// validate_db(Path::new("tasks-20241207-143015.db"))?;
+/// ```
+pub fn validate_db(path: &Path) -> Result<()> {
+    if !path.is_file() {
+        bail!("{} is not a file", path.display());
+    }
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .with_context(|| format!("{} is not a readable SQLite database", path.display()))?;
+    if check != "ok" {
+        bail!("{} is damaged: {}", path.display(), check);
+    }
+
+    for column in ["id", "description", "done", "birth"] {
+        if !column_exists(&conn, "tasks", column)? {
+            bail!(
+                "{} is not a todo-rs database: the tasks table is missing or has no '{}' column",
+                path.display(),
+                column
+            );
+        }
+    }
+
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "{} uses schema version {}, newer than the latest supported version {}",
+            path.display(),
+            version,
+            MIGRATIONS.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Size in bytes of the database file plus its WAL file, if there is one.
+///
+/// In-memory databases have no file and report a size of 0.
+fn database_size(conn: &Connection) -> Result<u64> {
+    let path = match conn.path() {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => return Ok(0),
+    };
+
+    let mut wal = path.clone().into_os_string();
+    wal.push("-wal");
+
+    let mut size = 0;
+    for file in [path, PathBuf::from(wal)] {
+        match std::fs::metadata(&file) {
+            Ok(meta) => size += meta.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read size of {}", file.display()))
+            }
+        }
+    }
+    Ok(size)
+}
+
+/// Handle database operations based on the provided command.
+///
+/// This is the main orchestration function that:
+/// 1. Opens a database connection with `open_connection`
+/// 2. Brings the schema up to date with `run_migrations`, after backing the
+///    database up with `backup_database` if an existing schema is upgraded
+/// 3. Executes the appropriate command
+/// 4. Handles all output and error reporting
+///
+/// # Arguments
+///
+/// * `db_path` - Path to the database file
+/// * `command` - The subcommand to execute
+/// * `profile` - The profile (task list) every task command works in
+/// * `format` - Output format used when listing tasks
+/// * `table` - Table style and date format used when printing tasks as text
+/// * `settings` - Config file defaults for options not given on the command line
+/// * `assume_yes` - Skip confirmation prompts, as requested with `--yes`
+/// * `quiet` - Don't print success messages, as requested with `--quiet`
+/// * `verbose` - Print every SQL statement and its timing to stderr, as
+///   requested with `--verbose`
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the operation completed successfully.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Database connection fails
+/// - A schema migration fails
+/// - The specific command operation fails
+/// - The command names a task that doesn't exist (`NoMatchingTask`)
+///
+/// # Examples
+///
+/// ```
+/// let db_path = get_db_path()?;
+/// let command = Commands::Add {
+///     descriptions: vec!["Test task".to_string()],
+///     priority: None,
+///     due: None,
+///     tags: vec![],
+///     note: None,
+///     recur: None,
+///     parent: None,
+///     unique: false,
+/// };
+/// let table = TableOptions {
+///     plain: false,
+///     max_width: 60,
+///     wrap: false,
+///     terminal_width: None,
+///     color: false,
+///     date_format: TIMESTAMP_FORMAT.to_string(),
+///     utc: false,
+/// };
+/// let settings = Settings::default();
+/// handle_db_operations(
+///     &db_path,
+///     command,
+///     DEFAULT_PROFILE,
+///     OutputFormat::Table,
+///     table,
+///     &settings,
+///     false,
+///     false,
+///     false,
+/// )?;
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn handle_db_operations(
+    db_path: &Path,
+    command: Commands,
+    profile: &str,
+    format: OutputFormat,
+    table: TableOptions,
+    settings: &Settings,
+    assume_yes: bool,
+    quiet: bool,
+    verbose: bool,
+) -> Result<()> {
+    validate_database(db_path)?;
+    let mut conn = open_connection(db_path)?;
+    if verbose {
+        log_statements(&conn);
+    }
+
+    let keep = settings
+        .migration_backups
+        .unwrap_or(DEFAULT_MIGRATION_BACKUPS);
+    if keep > 0 && needs_upgrade(&conn)? {
+        let path = backup_database(&conn, db_path, keep)
+            .context("Failed to back up the database before upgrading it")?;
+        if !quiet {
+            println!(
+                "Backed up the database to {} before upgrading it",
+                path.display()
+            );
+        }
+    }
+    run_migrations(&mut conn).context("Failed to migrate database schema")?;
+
+    let utc = settings.utc_timestamps.unwrap_or(true);
+    let max_length = match settings.max_description_length {
+        Some(0) => None,
+        limit => Some(limit.unwrap_or(DEFAULT_MAX_DESCRIPTION_LENGTH)),
+    };
+
+    match command {
+        Commands::Add {
+            descriptions,
+            priority,
+            due,
+            tags,
+            note,
+            recur,
+            parent,
+            unique,
+        } => {
+            let descriptions = descriptions
+                .iter()
+                .map(|description| normalize_description(description, max_length))
+                .collect::<Result<Vec<_>>>()?;
+
+            let ids = with_transaction(&mut conn, |tx| {
+                if let Some(parent) = parent {
+                    if Task::get(tx, profile, parent)?.is_none() {
+                        bail!(NoMatchingTask(format!("No task found with id: {}", parent)));
+                    }
+                }
+
+                descriptions
+                    .into_iter()
+                    .map(|description| {
+                        if unique {
+                            if let Some(task) =
+                                Task::find_by_description(tx, profile, &description)?
+                            {
+                                return Ok((task.id, false));
+                            }
+                        }
+
+                        let id = Task::add(tx, profile, description, priority, due, utc)?;
+                        Task::add_tags(tx, id, &tags)?;
+                        if let Some(note) = &note {
+                            Task::set_note(tx, profile, id, note)?;
+                        }
+                        if let Some(recur) = recur {
+                            Task::set_recurrence(tx, id, recur)?;
+                        }
+                        if let Some(parent) = parent {
+                            Task::set_parent(tx, id, parent)?;
+                        }
+                        Ok((id, true))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .context("Failed to add task")?;
+
+            for (id, added) in ids {
+                if quiet {
+                    // Keep the IDs available to scripts, whether new or existing
+                    println!("{}", id);
+                } else if added {
+                    println!("Task added successfully with id: {}", id);
+                } else {
+                    println!("Task already exists with id: {}", id);
+                }
+            }
+        }
+        Commands::List {
+            done,
+            pending,
+            sort,
+            reverse,
+            tag,
+            since,
+            until,
+            older_than,
+            limit,
+            offset,
+            archived,
+        } => {
+            if let (Some(since), Some(until)) = (since, until) {
+                if since > until {
+                    bail!(
+                        "--since {} is later than --until {}; swap the dates",
+                        since,
+                        until
+                    );
+                }
+            }
+
+            let options = ListOptions {
+                done: match (done, pending, older_than) {
+                    (true, _, _) => Some(true),
+                    (_, true, _) | (_, _, Some(_)) => Some(false),
+                    _ => None,
+                },
+                sort: sort.or(settings.default_sort).unwrap_or_default(),
+                reverse,
+                tag,
+                since,
+                until,
+                created_before: older_than.map(|days| {
+                    let now = Local::now().naive_local();
+                    now.checked_sub_days(Days::new(days.into()))
+                        .unwrap_or(NaiveDateTime::MIN)
+                }),
+                limit,
+                offset,
+                archived,
+            };
+            let tasks =
+                Task::list_tree(&conn, profile, &options).context("Failed to list tasks")?;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&tasks)?,
+                OutputFormat::Table | OutputFormat::Markdown | OutputFormat::Checklist
+                    if tasks.is_empty() =>
+                {
+                    println!("No tasks found")
+                }
+                OutputFormat::Table => io_utils::print_task_table(&tasks, &table),
+                OutputFormat::Markdown => io_utils::print_markdown_table(&tasks, &table),
+                OutputFormat::Tsv => io_utils::print_tsv(&tasks, &table)?,
+                OutputFormat::Checklist => io_utils::print_checklist(&tasks),
+            }
+        }
+        Commands::Search { term, fuzzy: true } => {
+            let matches =
+                Task::search_fuzzy(&conn, profile, &term).context("Failed to search tasks")?;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&matches)?,
+                OutputFormat::Table | OutputFormat::Markdown | OutputFormat::Checklist
+                    if matches.is_empty() =>
+                {
+                    println!("No tasks matching '{}'", term)
+                }
+                OutputFormat::Table => io_utils::print_match_table(&matches, &table),
+                OutputFormat::Markdown => io_utils::print_markdown_matches(&matches, &table),
+                OutputFormat::Tsv => io_utils::print_tsv_matches(&matches, &table)?,
+                OutputFormat::Checklist => {
+                    io_utils::print_checklist(matches.iter().map(|found| &found.task))
+                }
+            }
+        }
+        Commands::Search { term, fuzzy: false } => {
+            let tasks = Task::search(&conn, profile, &term).context("Failed to search tasks")?;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&tasks)?,
+                OutputFormat::Table | OutputFormat::Markdown | OutputFormat::Checklist
+                    if tasks.is_empty() =>
+                {
+                    println!("No tasks matching '{}'", term)
+                }
+                OutputFormat::Table => io_utils::print_task_table(&tasks, &table),
+                OutputFormat::Markdown => io_utils::print_markdown_table(&tasks, &table),
+                OutputFormat::Tsv => io_utils::print_tsv(&tasks, &table)?,
+                OutputFormat::Checklist => io_utils::print_checklist(&tasks),
+            }
+        }
+        Commands::Show { id } => {
+            let Some(task) = Task::get(&conn, profile, id).context("Failed to load task")? else {
+                bail!(NoMatchingTask(format!("No task found with id: {}", id)));
+            };
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&task)?,
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist => io_utils::print_task_details(&task, &table),
+            }
+        }
+        Commands::Today => {
+            let today = Local::now().naive_local().date();
+            let tasks =
+                Task::list_for_day(&conn, profile, today).context("Failed to list tasks")?;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&tasks)?,
+                OutputFormat::Table | OutputFormat::Markdown | OutputFormat::Checklist
+                    if tasks.is_empty() =>
+                {
+                    println!("No tasks created or due today.")
+                }
+                OutputFormat::Table => io_utils::print_task_table(&tasks, &table),
+                OutputFormat::Markdown => io_utils::print_markdown_table(&tasks, &table),
+                OutputFormat::Tsv => io_utils::print_tsv(&tasks, &table)?,
+                OutputFormat::Checklist => io_utils::print_checklist(&tasks),
+            }
+        }
+        Commands::History { days } => {
+            let now = Local::now().naive_local();
+            let first_day = now
+                .date()
+                .checked_sub_days(Days::new(u64::from(days - 1)))
+                .unwrap_or(NaiveDate::MIN);
+            let tasks =
+                Task::completed_between(&conn, profile, first_day.and_time(NaiveTime::MIN), now)
+                    .context("Failed to load completed tasks")?;
+            let history = CompletionDay::group(tasks);
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&history)?,
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist
+                    if history.is_empty() =>
+                {
+                    println!("No tasks completed in the last {} days", days)
+                }
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist => {
+                    for (i, day) in history.iter().enumerate() {
+                        if i > 0 {
+                            println!();
+                        }
+                        println!("{} ({} completed)", day.date, day.count);
+                        for task in &day.tasks {
+                            println!("  [{}] {}", task.id, task.description);
+                        }
+                    }
+                }
+            }
+        }
+        Commands::Stats => {
+            let stats = Task::stats(&conn, profile).context("Failed to compute task statistics")?;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&stats)?,
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist => {
+                    if stats.total == 0 {
+                        println!("No tasks yet");
+                        return Ok(());
+                    }
+
+                    println!(
+                        "Total: {}  Done: {}  Pending: {}",
+                        stats.total, stats.done, stats.pending
+                    );
+                    println!(
+                        "Progress: {}",
+                        io_utils::render_bar(stats.done, stats.total, STATS_BAR_WIDTH)
+                    );
+                    println!("Created in the last 7 days: {}", stats.created_last_7_days);
+                    if let Some(days) = stats.oldest_pending_days {
+                        println!("Oldest pending task: {} days old", days);
+                    }
+                }
+            }
+        }
+        Commands::Count { done, pending } => {
+            let status = match (done, pending) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+
+            if status.is_some() {
+                // A bare number is easiest to use from scripts and shell prompts
+                let count = Task::count(&conn, profile, status).context("Failed to count tasks")?;
+                println!("{}", count);
+                return Ok(());
+            }
+
+            let (pending, done) = Task::counts(&conn, profile).context("Failed to count tasks")?;
+            let total = pending + done;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&serde_json::json!({
+                    "pending": pending,
+                    "done": done,
+                    "total": total,
+                }))?,
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist => {
+                    println!("{} pending, {} done, {} total", pending, done, total)
+                }
+            }
+        }
+        Commands::Remove { ids, done: false } => {
+            let mut ids: Vec<i64> = ids.into_iter().flatten().collect();
+            ids.sort_unstable();
+            ids.dedup();
+
+            let missing = with_transaction(&mut conn, |tx| {
+                let mut missing = Vec::new();
+                for &id in &ids {
+                    if !Task::remove(tx, profile, id)? {
+                        missing.push(id);
+                    }
+                }
+                Ok(missing)
+            })
+            .context("Failed to remove task")?;
+
+            if let [id] = ids[..] {
+                if !missing.is_empty() {
+                    bail!(NoMatchingTask(format!("No task found with id: {}", id)));
+                }
+                if !quiet {
+                    println!("Task {} removed! Run `todo-rs undo` to restore it.", id);
+                }
+            } else {
+                if !quiet {
+                    println!(
+                        "Removed {} tasks, {} not found. Run `todo-rs undo` to restore them one at a time.",
+                        ids.len() - missing.len(),
+                        missing.len()
+                    );
+                }
+                if !missing.is_empty() {
+                    let missing: Vec<String> = missing.iter().map(i64::to_string).collect();
+                    bail!(NoMatchingTask(format!(
+                        "No tasks found with ids: {}",
+                        missing.join(", ")
+                    )));
+                }
+            }
+        }
+        Commands::Undo => {
+            let restored = with_transaction(&mut conn, |tx| Task::undo_last(tx, profile))
+                .context("Failed to restore task")?;
+
+            match restored {
+                Some(task) if !quiet => {
+                    println!("Restored task '{}' with id: {}", task.description, task.id)
+                }
+                Some(_) => {}
+                None => println!("Nothing to undo."),
+            }
+        }
+        Commands::Clear | Commands::Remove { done: true, .. } => {
+            if !io_utils::confirm("Remove all completed tasks?", Some(false), assume_yes)? {
+                if !quiet {
+                    println!("No tasks removed.");
+                }
+                return Ok(());
+            }
+
+            let removed =
+                Task::clear_completed(&conn, profile).context("Failed to clear completed tasks")?;
+            if !quiet {
+                println!("Removed {} completed tasks.", removed);
+            }
+        }
+        Commands::Reset => {
+            let prompt = "Delete every task in every profile and start over? This can't be undone.";
+            if !io_utils::confirm(prompt, Some(false), assume_yes)? {
+                if !quiet {
+                    println!("No tasks deleted.");
+                }
+                return Ok(());
+            }
+
+            let deleted = reset(&mut conn).context("Failed to reset the database")?;
+            if !quiet {
+                println!("Deleted {} tasks; the database is empty.", deleted);
+            }
+        }
+        Commands::Archive {
+            id: Some(id),
+            before: None,
+            ..
+        } => {
+            if !Task::archive(&conn, profile, id).context("Failed to archive task")? {
+                bail!(NoMatchingTask(format!(
+                    "Task {} is already archived or doesn't exist",
+                    id
+                )));
+            }
+            if !quiet {
+                println!("Task {} archived!", id);
+            }
+        }
+        Commands::Archive {
+            before: Some(before),
+            done,
+            ..
+        } => {
+            let archived = with_transaction(&mut conn, |tx| {
+                Task::archive_before(tx, profile, before, done)
+            })
+            .context("Failed to archive tasks")?;
+
+            if !quiet {
+                let kind = if done { "completed tasks" } else { "tasks" };
+                println!("Archived {} {} created before {}.", archived, kind, before);
+            }
+        }
+        Commands::Archive { .. } => unreachable!("clap requires either an ID or --before"),
+        Commands::Renumber => {
+            let prompt = "Renumber the tasks of every profile from 1? Task IDs will change.";
+            if !io_utils::confirm(prompt, Some(false), assume_yes)? {
+                if !quiet {
+                    println!("Task IDs left unchanged.");
+                }
+                return Ok(());
+            }
+
+            with_transaction(&mut conn, |tx| Task::renumber(tx))
+                .context("Failed to renumber tasks")?;
+            if !quiet {
+                println!("Task IDs renumbered.");
+            }
+        }
+        Commands::Done { ids } => {
+            let ids = if ids.is_empty() {
+                let options = ListOptions {
+                    done: Some(false),
+                    ..ListOptions::default()
+                };
+                let pending =
+                    Task::list_with(&conn, profile, &options).context("Failed to list tasks")?;
+                if pending.is_empty() {
+                    println!("No pending tasks.");
+                    return Ok(());
+                }
+
+                let chosen = io_utils::select_tasks(&pending)?;
+                if chosen.is_empty() {
+                    println!("No tasks marked as done.");
+                    return Ok(());
+                }
+                chosen
+            } else {
+                ids
+            };
+
+            let skipped = with_transaction(&mut conn, |tx| {
+                let mut skipped = Vec::new();
+                for &id in &ids {
+                    if !Task::mark_done(tx, profile, id, utc)
+                        .context("Failed to mark task as done")?
+                    {
+                        skipped.push(id);
+                    }
+                }
+                Ok(skipped)
+            })?;
+
+            if let [id] = ids[..] {
+                if !skipped.is_empty() {
+                    bail!(NoMatchingTask(format!(
+                        "Task {} already completed or doesn't exist",
+                        id
+                    )));
+                }
+                if !quiet {
+                    println!("Task {} marked as done!", id);
+                }
+            } else {
+                if !quiet {
+                    println!(
+                        "Marked {} tasks done, {} already completed or missing.",
+                        ids.len() - skipped.len(),
+                        skipped.len()
+                    );
+                }
+                if !skipped.is_empty() {
+                    let skipped: Vec<String> = skipped.iter().map(i64::to_string).collect();
+                    bail!(NoMatchingTask(format!(
+                        "Tasks already completed or missing: {}",
+                        skipped.join(", ")
+                    )));
+                }
+            }
+        }
+        Commands::Undone { id } => {
+            let updated =
+                Task::mark_undone(&conn, profile, id).context("Failed to mark task as not done")?;
+
+            if !updated {
+                bail!(NoMatchingTask(format!(
+                    "Task {} is not completed or doesn't exist",
+                    id
+                )));
+            }
+            if !quiet {
+                println!("Task {} marked as not done!", id);
+            }
+        }
+        Commands::Edit {
+            id,
+            description,
+            note,
+        } => {
+            // Settle the new description before writing anything, so an
+            // invalid one can't leave the note saved on its own
+            let description = match (description, &note) {
+                (Some(description), _) => Some(normalize_description(&description, max_length)?),
+                // Only the notes were asked for; don't open the editor
+                (None, Some(_)) => None,
+                (None, None) => {
+                    let Some(task) =
+                        Task::get(&conn, profile, id).context("Failed to load task")?
+                    else {
+                        bail!(NoMatchingTask(format!("No task found with id: {}", id)));
+                    };
+
+                    let edited = io_utils::edit_in_editor(&task.description)?;
+                    if edited.is_empty() || edited == task.description {
+                        if !quiet {
+                            println!("Task {} left unchanged.", id);
+                        }
+                        return Ok(());
+                    }
+                    Some(normalize_description(&edited, max_length)?)
+                }
+            };
+
+            let updated = with_transaction(&mut conn, |tx| {
+                if let Some(note) = &note {
+                    if !Task::set_note(tx, profile, id, note)? {
+                        return Ok(false);
+                    }
+                }
+                match description {
+                    Some(description) => Task::edit(tx, profile, id, description),
+                    None => Ok(true),
+                }
+            })
+            .context("Failed to edit task")?;
+
+            if !updated {
+                bail!(NoMatchingTask(format!("No task found with id: {}", id)));
+            }
+            if !quiet {
+                println!("Task {} updated!", id);
+            }
+        }
+        Commands::Duplicate { id } => {
+            let copy = with_transaction(&mut conn, |tx| Task::duplicate(tx, profile, id, utc))
+                .context("Failed to duplicate task")?;
+
+            let Some(copy) = copy else {
+                bail!(NoMatchingTask(format!("No task found with id: {}", id)));
+            };
+            if quiet {
+                // Keep the new ID available to scripts
+                println!("{}", copy);
+            } else {
+                println!("Task {} duplicated as task {}", id, copy);
+            }
+        }
+        Commands::Move { id, position } => {
+            let moved = with_transaction(&mut conn, |tx| Task::move_to(tx, profile, id, position))
+                .context("Failed to move task")?;
+
+            let Some(position) = moved else {
+                bail!(NoMatchingTask(format!("No task found with id: {}", id)));
+            };
+            if !quiet {
+                println!("Task {} moved to position {}.", id, position);
+            }
+        }
+        Commands::Export { path, format } => {
+            let file = File::create(&path)
+                .with_context(|| format!("Failed to create export file {}", path.display()))?;
+            let mut writer = BufWriter::new(file);
+
+            let count = match format {
+                ExportFormat::Csv => Task::export_csv(&conn, profile, &mut writer),
+                ExportFormat::Json => Task::export_json(&conn, profile, &mut writer),
+            }
+            .context("Failed to export tasks")?;
+
+            writer
+                .flush()
+                .with_context(|| format!("Failed to write export file {}", path.display()))?;
+            if !quiet {
+                println!("Exported {} tasks to {}", count, path.display());
+            }
+        }
+        Commands::Import { path, format } => {
+            let file = File::open(&path)
+                .with_context(|| format!("Failed to open import file {}", path.display()))?;
+            let reader = BufReader::new(file);
+
+            let count = match format {
+                ExportFormat::Csv => Task::import_csv(&mut conn, profile, reader, utc),
+                ExportFormat::Json => Task::import_json(&mut conn, profile, reader, utc),
+            }
+            .with_context(|| format!("Failed to import tasks from {}", path.display()))?;
+
+            if !quiet {
+                println!("Imported {} tasks from {}", count, path.display());
+            }
+        }
+        Commands::Backup { dest } => {
+            let path = backup(&conn, dest.as_deref())?;
+
+            if quiet {
+                println!("{}", path.display());
+            } else {
+                println!("Backup written to {}", path.display());
+            }
+        }
+        Commands::Restore { src } => {
+            validate_db(&src).context("Refusing to restore")?;
+
+            let prompt = format!(
+                "Replace all tasks in {} with the contents of {}?",
+                db_path.display(),
+                src.display()
+            );
+            if !io_utils::confirm(&prompt, Some(false), assume_yes)? {
+                if !quiet {
+                    println!("Database left unchanged.");
+                }
+                return Ok(());
+            }
+
+            // The backup API copies page by page through SQLite, so the WAL
+            // and any other connection see a consistent database
+            conn.restore(MAIN_DB, &src, None::<fn(rusqlite::backup::Progress)>)
+                .with_context(|| format!("Failed to restore from {}", src.display()))?;
+            run_migrations(&mut conn).context("Failed to migrate restored database")?;
+
+            if !quiet {
+                println!("Database restored from {}", src.display());
+            }
+        }
+        Commands::Profiles => {
+            let profiles = Task::profiles(&conn).context("Failed to list profiles")?;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&profiles)?,
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist
+                    if profiles.is_empty() =>
+                {
+                    println!("No profiles have tasks yet.")
+                }
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist => {
+                    for name in profiles {
+                        println!("{}", name);
+                    }
+                }
+            }
+        }
+        Commands::Completions { .. } => {
+            unreachable!("completions are printed before the database is opened")
+        }
+        Commands::Vacuum => {
+            let report = vacuum(&conn)?;
+
+            match format {
+                OutputFormat::Json => io_utils::print_json(&report)?,
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist
+                    if quiet && report.integrity_ok => {}
+                OutputFormat::Table
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+                | OutputFormat::Checklist => {
+                    if report.integrity_ok {
+                        println!("Integrity check passed.");
+                    } else {
+                        println!("Integrity check failed:");
+                        for problem in &report.problems {
+                            println!("  {}", problem);
+                        }
+                        println!("Database was not vacuumed.");
+                    }
+                    println!(
+                        "Size: {} bytes before, {} bytes after",
+                        report.size_before, report.size_after
+                    );
+                }
+            }
+
+            if !report.integrity_ok {
+                bail!("Database integrity check failed");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    use crate::config::tests::with_env;
+
+    /// Create an up-to-date database in a fresh temporary directory.
+    ///
+    /// The directory and the database are deleted when the returned
+    /// `TempDir` is dropped, so keep it alive while the connection is used.
+    pub(crate) fn temp_db() -> (TempDir, Connection) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.db");
+        create_database(&path).unwrap();
+        let conn = open_connection(&path).unwrap();
+        (dir, conn)
+    }
+
+    /// The tasks table as created by the first release, before schema versioning.
+    const BASELINE_SCHEMA: &str = "CREATE TABLE tasks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        description TEXT NOT NULL,
+        done BOOLEAN NOT NULL DEFAULT 0,
+        birth TEXT NOT NULL
+    )";
+
+    /// Create a database with the baseline schema and one task, at version 0.
+    fn baseline_db(path: &Path) -> Connection {
+        let conn = open_connection(path).unwrap();
+        conn.execute(BASELINE_SCHEMA, []).unwrap();
+        conn.execute(
+            "INSERT INTO tasks (description, done, birth) VALUES ('Old task', 1, '2024-01-02 03:04:05')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    /// Describe a database's schema in a form that doesn't depend on how it
+    /// was built: every table's columns, then every index and trigger.
+    ///
+    /// The `sql` text in `sqlite_master` can't be compared directly, since
+    /// `ALTER TABLE` rewrites it differently than a single `CREATE TABLE`.
+    fn schema_of(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT type, name, tbl_name FROM sqlite_master
+                 WHERE name NOT LIKE 'sqlite_%' ORDER BY type, name",
+            )
+            .unwrap();
+        let objects: Vec<(String, String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+
+        let mut schema = Vec::new();
+        for (kind, name, table) in objects {
+            schema.push(format!("{} {} on {}", kind, name, table));
+            let pragma = match kind.as_str() {
+                "table" => "table_info",
+                "index" => "index_info",
+                _ => continue,
+            };
+            let mut info = conn
+                .prepare(&format!("PRAGMA {}({})", pragma, name))
+                .unwrap();
+            let count = info.column_count();
+            let rows = info
+                .query_map([], |row| {
+                    (0..count)
+                        .map(|i| row.get::<_, rusqlite::types::Value>(i))
+                        .collect::<rusqlite::Result<Vec<_>>>()
+                })
+                .unwrap();
+            for row in rows {
+                schema.push(format!("  {:?}", row.unwrap()));
+            }
+        }
+        schema
+    }
+
+    /// Run a command through `handle_db_operations` with default options.
+    fn run_command(db_path: &Path, command: Commands, settings: &Settings) -> Result<()> {
+        let table = TableOptions {
+            plain: true,
+            max_width: 60,
+            wrap: false,
+            terminal_width: None,
+            color: false,
+            date_format: crate::models::TIMESTAMP_FORMAT.to_string(),
+            utc: false,
+        };
+        handle_db_operations(
+            db_path,
+            command,
+            "default",
+            OutputFormat::Table,
+            table,
+            settings,
+            true,
+            true,
+            false,
+        )
+    }
+
+    /// Names of the backups taken of `tasks.db` in `dir`, oldest first.
+    fn backups_in(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("tasks.db.bak-"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn task_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn with_transaction_commits_on_success() {
+        let (_dir, mut conn) = temp_db();
+
+        let ids = with_transaction(&mut conn, |tx| {
+            let first = Task::add(tx, "default", "First".to_string(), None, None, false)?;
+            let second = Task::add(tx, "default", "Second".to_string(), None, None, false)?;
+            Ok(vec![first, second])
+        })
+        .unwrap();
+
+        assert_eq!(ids.len(), 2);
+        assert_eq!(task_count(&conn), 2);
+    }
+
+    #[test]
+    fn with_transaction_rolls_back_on_error() {
+        let (_dir, mut conn) = temp_db();
+
+        let result: Result<()> = with_transaction(&mut conn, |tx| {
+            Task::add(tx, "default", "First".to_string(), None, None, false)?;
+            Task::add(tx, "default", "Second".to_string(), None, None, false)?;
+            anyhow::bail!("failure partway through");
+        });
+
+        assert_eq!(result.unwrap_err().to_string(), "failure partway through");
+        assert_eq!(task_count(&conn), 0);
+    }
+
+    #[test]
+    fn with_transaction_rolls_back_on_failed_statement() {
+        let (_dir, mut conn) = temp_db();
+
+        // The second insert violates the NOT NULL constraint on description
+        let result = with_transaction(&mut conn, |tx| {
+            Task::add(tx, "default", "First".to_string(), None, None, false)?;
+            tx.execute(
+                "INSERT INTO tasks (description, birth) VALUES (NULL, '2024-01-01 00:00:00')",
+                [],
+            )?;
+            Ok(())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(task_count(&conn), 0);
+    }
+
+    #[test]
+    fn run_migrations_upgrades_a_version_0_database() {
+        let dir = TempDir::new().unwrap();
+        let mut conn = baseline_db(&dir.path().join("tasks.db"));
+        assert_eq!(user_version(&conn), 0);
+
+        run_migrations(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        for column in [
+            "priority",
+            "due",
+            "done_at",
+            "notes",
+            "position",
+            "recur",
+            "list_name",
+            "parent_id",
+            "archived",
+            "recurred_from",
+        ] {
+            assert!(column_exists(&conn, "tasks", column).unwrap(), "{}", column);
+        }
+        assert!(column_exists(&conn, "task_tags", "tag").unwrap());
+        assert!(column_exists(&conn, "undo_log", "archived").unwrap());
+
+        let task = Task::get(&conn, "default", 1).unwrap().unwrap();
+        assert_eq!(task.description, "Old task");
+        assert!(task.done);
+        assert_eq!(task.priority, 0);
+        assert!(!task.archived);
+    }
+
+    #[test]
+    fn run_migrations_is_a_no_op_when_up_to_date() {
+        let (_dir, mut conn) = temp_db();
+        Task::add(&conn, "default", "Task".to_string(), None, None, false).unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        assert_eq!(task_count(&conn), 1);
+    }
+
+    #[test]
+    fn run_migrations_rejects_a_newer_schema() {
+        let (_dir, mut conn) = temp_db();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+
+        let err = run_migrations(&mut conn).unwrap_err();
+
+        assert!(err.to_string().contains("newer than the latest supported"));
+    }
+
+    #[test]
+    fn open_connection_enables_wal_and_a_busy_timeout() {
+        let (_dir, conn) = temp_db();
+
+        let mode: String = conn
+            .pragma_query_value(None, "journal_mode", |row| row.get(0))
+            .unwrap();
+        let timeout: u64 = conn
+            .pragma_query_value(None, "busy_timeout", |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(mode, "wal");
+        assert_eq!(timeout, BUSY_TIMEOUT.as_millis() as u64);
+    }
+
+    #[test]
+    fn writes_do_not_wait_for_open_readers() {
+        let (dir, reader) = temp_db();
+        let path = dir.path().join("tasks.db");
+        Task::add(&reader, "default", "First".to_string(), None, None, false).unwrap();
+
+        // Hold a read transaction open, as a long `list` would
+        reader.execute_batch("BEGIN").unwrap();
+        assert_eq!(task_count(&reader), 1);
+
+        // Without WAL the commit would need the reader to finish first; with
+        // no busy timeout at all it must succeed straight away
+        let writer = open_connection(&path).unwrap();
+        writer.busy_timeout(Duration::ZERO).unwrap();
+        Task::add(&writer, "default", "Second".to_string(), None, None, false).unwrap();
+
+        // The reader keeps its snapshot until its transaction ends
+        assert_eq!(task_count(&reader), 1);
+        reader.execute_batch("COMMIT").unwrap();
+        assert_eq!(task_count(&reader), 2);
+    }
+
+    #[test]
+    fn writers_wait_for_each_other_instead_of_failing() {
+        let (dir, mut first) = temp_db();
+        let path = dir.path().join("tasks.db");
+
+        let tx = first.transaction().unwrap();
+        Task::add(&tx, "default", "First".to_string(), None, None, false).unwrap();
+
+        let second = std::thread::spawn(move || {
+            let conn = open_connection(&path).unwrap();
+            Task::add(&conn, "default", "Second".to_string(), None, None, false)
+        });
+
+        // The second writer is blocked until this transaction commits
+        std::thread::sleep(Duration::from_millis(200));
+        tx.commit().unwrap();
+
+        second.join().unwrap().unwrap();
+        assert_eq!(task_count(&first), 2);
+    }
+
+    #[test]
+    fn todo_rs_db_is_used_verbatim() {
+        for value in ["/srv/todo/tasks.db", "relative/tasks.db"] {
+            with_env(&[(DB_PATH_ENV, Some(value))], || {
+                assert_eq!(custom_db_path(None).unwrap(), Some(PathBuf::from(value)));
+            });
+        }
+    }
+
+    #[test]
+    fn db_flag_takes_precedence_over_todo_rs_db() {
+        with_env(&[(DB_PATH_ENV, Some("/srv/todo/tasks.db"))], || {
+            let path = custom_db_path(Some(Path::new("/tmp/flag.db"))).unwrap();
+            assert_eq!(path, Some(PathBuf::from("/tmp/flag.db")));
+        });
+    }
+
+    #[test]
+    fn unset_or_empty_todo_rs_db_falls_back_to_the_default() {
+        for value in [None, Some("")] {
+            with_env(&[(DB_PATH_ENV, value)], || {
+                assert_eq!(custom_db_path(None).unwrap(), None);
+            });
+        }
+    }
+
+    #[test]
+    fn edit_with_an_invalid_description_keeps_the_old_note() {
+        let (dir, conn) = temp_db();
+        let id = Task::add(&conn, "default", "Task".to_string(), None, None, false).unwrap();
+        Task::set_note(&conn, "default", id, "Old note").unwrap();
+
+        let edit = Commands::Edit {
+            id,
+            description: Some("x".repeat(DEFAULT_MAX_DESCRIPTION_LENGTH + 1)),
+            note: Some("New note".to_string()),
+        };
+        let err =
+            run_command(&dir.path().join("tasks.db"), edit, &Settings::default()).unwrap_err();
+
+        assert!(
+            err.to_string().starts_with("Task description is"),
+            "{}",
+            err
+        );
+        let task = Task::get(&conn, "default", id).unwrap().unwrap();
+        assert_eq!(task.description, "Task");
+        assert_eq!(task.notes.as_deref(), Some("Old note"));
+    }
+
+    #[test]
+    fn edit_updates_the_note_and_description_together() {
+        let (dir, conn) = temp_db();
+        let id = Task::add(&conn, "default", "Task".to_string(), None, None, false).unwrap();
+
+        let edit = Commands::Edit {
+            id,
+            description: Some("  Renamed  ".to_string()),
+            note: Some("New note".to_string()),
+        };
+        run_command(&dir.path().join("tasks.db"), edit, &Settings::default()).unwrap();
+
+        let task = Task::get(&conn, "default", id).unwrap().unwrap();
+        assert_eq!(task.description, "Renamed");
+        assert_eq!(task.notes.as_deref(), Some("New note"));
+    }
+
+    #[test]
+    fn edit_of_a_missing_task_changes_nothing() {
+        let (dir, _conn) = temp_db();
+
+        let edit = Commands::Edit {
+            id: 42,
+            description: Some("Renamed".to_string()),
+            note: Some("New note".to_string()),
+        };
+        let err =
+            run_command(&dir.path().join("tasks.db"), edit, &Settings::default()).unwrap_err();
+
+        assert!(err.downcast_ref::<NoMatchingTask>().is_some());
+    }
+
+    #[test]
+    fn opening_an_old_database_backs_it_up_before_migrating() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.db");
+        drop(baseline_db(&path));
+
+        let count = Commands::Count {
+            done: false,
+            pending: false,
+        };
+        run_command(&path, count, &Settings::default()).unwrap();
+
+        let backups = backups_in(dir.path());
+        assert_eq!(backups.len(), 1);
+
+        // The backup holds the database as it was before the upgrade
+        let backup = Connection::open(dir.path().join(&backups[0])).unwrap();
+        assert_eq!(user_version(&backup), 0);
+        assert_eq!(task_count(&backup), 1);
+        assert!(!column_exists(&backup, "tasks", "priority").unwrap());
+
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn opening_an_up_to_date_database_takes_no_backup() {
+        let (dir, _conn) = temp_db();
+
+        let count = Commands::Count {
+            done: false,
+            pending: false,
+        };
+        run_command(&dir.path().join("tasks.db"), count, &Settings::default()).unwrap();
+
+        assert!(backups_in(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn migration_backups_can_be_turned_off() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.db");
+        drop(baseline_db(&path));
+
+        let settings = Settings {
+            migration_backups: Some(0),
+            ..Settings::default()
+        };
+        let count = Commands::Count {
+            done: false,
+            pending: false,
+        };
+        run_command(&path, count, &settings).unwrap();
+
+        assert!(backups_in(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn backup_database_keeps_only_the_newest_backups() {
+        let (dir, conn) = temp_db();
+        let path = dir.path().join("tasks.db");
+        for stamp in ["20200101-000000", "20210101-000000", "20220101-000000"] {
+            std::fs::write(dir.path().join(format!("tasks.db.bak-{}", stamp)), "").unwrap();
+        }
+
+        let newest = backup_database(&conn, &path, 2).unwrap();
+
+        let newest = newest.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(
+            backups_in(dir.path()),
+            ["tasks.db.bak-20220101-000000".to_string(), newest]
+        );
+    }
+
+    #[test]
+    fn validate_database_accepts_a_healthy_database() {
+        let (dir, conn) = temp_db();
+        Task::add(&conn, "default", "Task".to_string(), None, None, false).unwrap();
+
+        validate_database(&dir.path().join("tasks.db")).unwrap();
+    }
+
+    #[test]
+    fn validate_database_rejects_a_non_sqlite_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.db");
+        std::fs::write(&path, "- [ ] buy milk\n- [ ] call mom\n".repeat(100)).unwrap();
+
+        let err = validate_database(&path).unwrap_err().to_string();
+
+        assert!(err.contains("appears corrupt"), "{}", err);
+        assert!(err.contains("copy a backup over it"), "{}", err);
+    }
+
+    #[test]
+    fn validate_database_rejects_a_truncated_file() {
+        let (dir, conn) = temp_db();
+        Task::add(&conn, "default", "Task".to_string(), None, None, false).unwrap();
+        conn.pragma_update(None, "wal_checkpoint", "TRUNCATE")
+            .unwrap();
+        drop(conn);
+
+        let path = dir.path().join("tasks.db");
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..50]).unwrap();
+
+        let err = validate_database(&path).unwrap_err().to_string();
+
+        assert!(err.contains("appears corrupt"), "{}", err);
+    }
+
+    #[test]
+    fn create_database_makes_a_queryable_database() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("tasks.db");
+
+        create_database(&path).unwrap();
+
+        // A plain connection, without the migrations handle_db_operations runs
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        assert_eq!(task_count(&conn), 0);
+        validate_database(&path).unwrap();
+    }
+
+    #[test]
+    fn created_and_migrated_databases_have_the_same_schema() {
+        let dir = TempDir::new().unwrap();
+
+        let created_path = dir.path().join("created.db");
+        create_database(&created_path).unwrap();
+        let created = open_connection(&created_path).unwrap();
+
+        let mut migrated = baseline_db(&dir.path().join("migrated.db"));
+        run_migrations(&mut migrated).unwrap();
+
+        let schema = schema_of(&created);
+        assert!(schema.contains(&"table tasks on tasks".to_string()));
+        assert_eq!(user_version(&created), user_version(&migrated));
+        assert_eq!(schema, schema_of(&migrated));
+    }
 }
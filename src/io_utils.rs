@@ -1,10 +1,20 @@
 //! Input/Output utility functions module.
 //!
 //! This module provides utility functions for handling user input and output
-//! operations. Currently, it focuses on user confirmation prompts but may
-//! be extended with additional I/O utilities in the future.
+//! operations, such as user confirmation prompts, rendering task listings
+//! as a table or JSON, and reading and writing CSV data.
 
-use std::io::{self, Write};
+use std::borrow::Cow;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::Serialize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::args::ColorChoice;
+use crate::models::{SearchMatch, Task};
 
 /// Ask the user for a yes/no confirmation.
 ///
@@ -12,9 +22,14 @@ use std::io::{self, Write};
 /// It accepts 'Y' or 'N' (case insensitive) as valid responses and will
 /// continue prompting until a valid response is received.
 ///
+/// The choices are appended to the prompt, with the default answer in
+/// capitals: `(Y/n)` when it is yes, `(y/N)` when it is no and `(Y/N)`
+/// when there is none.
+///
 /// # Arguments
 ///
-/// * `prompt` - The message to display to the user
+/// * `prompt` - The question to display to the user, without the choices
+/// * `default` - The answer used for an empty line, if any
 ///
 /// # Returns
 ///
@@ -24,43 +39,1463 @@ use std::io::{self, Write};
 ///
 /// - The function will loop until a valid response is received
 /// - Input is case-insensitive ('y', 'Y', 'n', 'N' are all valid)
+/// - An empty line returns `default` when one is given
 /// - Invalid input will show an error message and prompt again
-/// - IO errors during input reading will be displayed but won't crash the program
+/// - End of input (e.g. `echo | todo-rs clear`) returns `default`, or
+///   `false` without one, instead of prompting forever
+///
+/// # Errors
+///
+/// Returns an error if stdout cannot be flushed (for example a closed pipe)
+/// or stdin cannot be read.
 ///
 /// # Examples
 ///
 /// ```
 /// use todo_rs::io_utils::ask_user_confirmation;
 ///
-/// let confirmed = ask_user_confirmation("Do you want to continue? (Y/N): ");
+/// // Prints "Do you want to continue? (y/N): "
+/// let confirmed = ask_user_confirmation("Do you want to continue?", Some(false))?;
 /// if confirmed {
 ///     println!("User confirmed!");
 /// } else {
 ///     println!("User declined.");
 /// }
 /// ```
+pub fn ask_user_confirmation(prompt: &str, default: Option<bool>) -> io::Result<bool> {
+    read_confirmation(&mut io::stdin().lock(), &mut io::stdout(), prompt, default)
+}
+
+/// Prompt for a yes/no answer on arbitrary input and output streams.
 ///
-/// # Panics
+/// This holds the logic of `ask_user_confirmation`, which calls it with
+/// stdin and stdout; taking the streams as parameters lets it be driven by
+/// scripted input.
 ///
-/// This function will panic if stdout cannot be flushed, which typically
-/// indicates a serious system-level issue.
-pub fn ask_user_confirmation(prompt: &str) -> bool {
+/// # Arguments
+///
+/// * `input` - Where answers are read from
+/// * `output` - Where the prompt and error messages are written
+/// * `prompt` - The question to display, without the choices
+/// * `default` - The answer used for an empty line or end of input, if any
+///
+/// # Returns
+///
+/// Returns `true` for a yes answer, `false` for a no answer.
+///
+/// # Errors
+///
+/// Returns an error if writing the prompt or reading the answer fails.
+fn read_confirmation<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    prompt: &str,
+    default: Option<bool>,
+) -> io::Result<bool> {
+    let choices = match default {
+        Some(true) => "Y/n",
+        Some(false) => "y/N",
+        None => "Y/N",
+    };
+
     loop {
-        print!("{prompt}");
-        io::stdout().flush().expect("Failed to flush stdout");
+        write!(output, "{prompt} ({choices}): ")?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            // Finish the prompt line so later output starts on its own line
+            writeln!(output)?;
+            return Ok(default.unwrap_or(false));
+        }
+
+        match (line.trim().to_ascii_uppercase().as_str(), default) {
+            ("Y", _) => return Ok(true),
+            ("N", _) => return Ok(false),
+            ("", Some(answer)) => return Ok(answer),
+            _ => writeln!(output, "Please enter 'Y' or 'N'.")?,
+        }
+    }
+}
+
+/// Let the user pick tasks from a numbered menu.
+///
+/// Prints the tasks numbered from 1 and reads a line of space-separated
+/// numbers. An empty line or end of input selects nothing; numbers that are
+/// out of range or not numbers at all prompt again.
+///
+/// # Arguments
+///
+/// * `tasks` - The tasks to choose from
+///
+/// # Returns
+///
+/// Returns the IDs of the chosen tasks, in menu order and without duplicates.
+///
+/// # Errors
+///
+/// Returns an error if stdin is not a terminal, since the menu could not be
+/// answered, or if reading or writing fails.
+///
+/// # Examples
+///
+/// ```
+/// let ids = select_tasks(&pending)?;
+/// for id in ids {
+///     Task::mark_done(&conn, "default", id, false)?;
+/// }
+/// ```
+pub fn select_tasks(tasks: &[Task]) -> Result<Vec<i64>> {
+    if !io::stdin().is_terminal() {
+        bail!("No task IDs given and stdin is not a terminal; pass the IDs explicitly, e.g. `todo-rs done 3 5`");
+    }
+
+    let mut output = io::stdout();
+    for (i, task) in tasks.iter().enumerate() {
+        writeln!(output, "{:>3}) [{}] {}", i + 1, task.id, task.description)?;
+    }
+
+    let chosen = read_selection(&mut io::stdin().lock(), &mut output, tasks.len())
+        .context("Failed to read selection")?;
+    Ok(chosen.into_iter().map(|i| tasks[i].id).collect())
+}
+
+/// Read a selection of menu numbers from arbitrary input and output streams.
+///
+/// Menu numbers start at 1; the returned indices start at 0, are sorted
+/// and contain no duplicates.
+fn read_selection<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    count: usize,
+) -> io::Result<Vec<usize>> {
+    loop {
+        write!(output, "Tasks to complete (e.g. 1 3), or Enter for none: ")?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(Vec::new());
+        }
+
+        let parsed: Option<Vec<usize>> = line
+            .split_whitespace()
+            .map(|word| {
+                word.parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=count).contains(n))
+            })
+            .collect();
+
+        match parsed {
+            Some(mut numbers) => {
+                numbers.sort_unstable();
+                numbers.dedup();
+                return Ok(numbers.into_iter().map(|n| n - 1).collect());
+            }
+            None => writeln!(output, "Please enter numbers between 1 and {}.", count)?,
+        }
+    }
+}
+
+/// Ask for confirmation, honouring the global `--yes` flag.
+///
+/// With `assume_yes` the prompt is skipped entirely. Otherwise the user is
+/// asked with `ask_user_confirmation`, but only when stdin is a terminal:
+/// a script without `--yes` gets an error instead of a prompt it can't
+/// answer.
+///
+/// # Arguments
+///
+/// * `prompt` - The question to display to the user, without the choices
+/// * `default` - The answer used for an empty line, if any
+/// * `assume_yes` - Whether `--yes` was passed
+///
+/// # Returns
+///
+/// Returns `true` if the action was confirmed, `false` if it was declined.
+///
+/// # Errors
+///
+/// Returns an error if stdin is not a terminal and `assume_yes` is not
+/// set, or if the prompt cannot be written or answered.
+///
+/// # Examples
+///
+/// ```
+/// if confirm("Remove all completed tasks?", Some(false), cli.yes)? {
+///     Task::clear_completed(&conn, "default")?;
+/// }
+/// ```
+pub fn confirm(prompt: &str, default: Option<bool>, assume_yes: bool) -> Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+
+    if !io::stdin().is_terminal() {
+        bail!("Confirmation needed but stdin is not a terminal; pass --yes to proceed");
+    }
+
+    ask_user_confirmation(prompt, default).context("Failed to read confirmation")
+}
+
+/// Let the user edit text in their editor.
+///
+/// Writes `initial` to a temporary file, opens it with the command in the
+/// `EDITOR` environment variable (falling back to `notepad` on Windows and
+/// `vi` elsewhere) and reads the saved file back once the editor exits.
+/// `EDITOR` may include arguments, e.g. `code --wait`. The temporary file
+/// gets a random name and is only readable by the current user, and it is
+/// always removed afterwards.
+///
+/// # Arguments
+///
+/// * `initial` - The text to start editing from
+///
+/// # Returns
+///
+/// Returns the edited text with surrounding whitespace removed.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file cannot be written or read, the
+/// editor cannot be started, or the editor exits with a non-zero status,
+/// in which case the edit should be abandoned.
+///
+/// # Examples
+///
+/// ```
+/// let description = edit_in_editor(&task.description)?;
+/// Task::edit(&conn, "default", task.id, description)?;
+/// ```
+pub fn edit_in_editor(initial: &str) -> Result<String> {
+    let default_editor = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| default_editor.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(default_editor);
+
+    // A fresh file with a random name, readable only by the user, so another
+    // user can't plant a symlink at a predictable path in a shared temp dir
+    let mut file = tempfile::Builder::new()
+        .prefix("todo-rs-edit-")
+        .suffix(".txt")
+        .tempfile()
+        .context("Failed to create temporary file")?;
+    writeln!(file, "{}", initial)
+        .with_context(|| format!("Failed to write temporary file {}", file.path().display()))?;
+    // Close the file so the editor can replace it; it is deleted when dropped
+    let path = file.into_temp_path();
+
+    let result = Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor))
+        .and_then(|status| {
+            if !status.success() {
+                bail!(
+                    "Editor '{}' exited with {}; nothing was changed",
+                    editor,
+                    status
+                );
+            }
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read temporary file {}", path.display()))
+        });
+
+    Ok(result?.trim().to_string())
+}
+
+/// Column headers shared by both task table styles.
+const TABLE_HEADERS: [&str; 8] = [
+    "ID",
+    "DONE",
+    "PRIORITY",
+    "BIRTH",
+    "DUE",
+    "DONE AT",
+    "TAGS",
+    "DESCRIPTION",
+];
+
+/// Column headers of fuzzy search results, a score before the task columns.
+const MATCH_HEADERS: [&str; 9] = [
+    "SCORE",
+    "ID",
+    "DONE",
+    "PRIORITY",
+    "BIRTH",
+    "DUE",
+    "DONE AT",
+    "TAGS",
+    "DESCRIPTION",
+];
+
+/// Options controlling how task tables are rendered.
+///
+/// # Fields
+///
+/// * `plain` - Use the fixed-width `|`-separated layout instead of a bordered table
+/// * `max_width` - Maximum display width of the description column in bordered tables
+/// * `wrap` - Wrap long descriptions onto extra lines instead of truncating them
+/// * `terminal_width` - Width of the terminal the table is printed to, if known
+/// * `color` - Whether rows are styled with ANSI escape codes
+/// * `date_format` - chrono format string used to display `birth` and `done_at`
+/// * `utc` - Show `birth` and `done_at` in UTC instead of local time
+#[derive(Debug, Clone)]
+pub struct TableOptions {
+    pub plain: bool,
+    pub max_width: usize,
+    pub wrap: bool,
+    pub terminal_width: Option<usize>,
+    pub color: bool,
+    pub date_format: String,
+    pub utc: bool,
+}
+
+/// Widths of every column but the last in the plain table layout.
+const PLAIN_WIDTHS: [usize; 7] = [8, 8, 8, 19, 20, 19, 15];
+
+/// Widths of every column but the last in the plain layout of fuzzy search results.
+const MATCH_PLAIN_WIDTHS: [usize; 8] = [6, 8, 8, 8, 19, 20, 19, 15];
+
+/// ANSI style for completed tasks (dim green).
+const DONE_STYLE: &str = "2;32";
+
+/// ANSI style for overdue tasks (red).
+const OVERDUE_STYLE: &str = "31";
+
+/// ANSI style for high-priority tasks (bold).
+const HIGH_PRIORITY_STYLE: &str = "1";
+
+/// Get the width of the terminal stdout is printed to.
+///
+/// # Returns
+///
+/// Returns the width in columns, or `None` when stdout is not a terminal or
+/// its size can't be determined.
+///
+/// # Examples
+///
+/// ```
+/// if let Some(width) = terminal_width() {
+///     println!("Terminal is {} columns wide", width);
+/// }
+/// ```
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width.into())
+}
 
-        let mut input = String::new();
-        if let Err(e) = io::stdin().read_line(&mut input) {
-            eprintln!("Failed to read input: {e}");
+/// Decide whether output should be colored.
+///
+/// `Always` and `Never` are taken as given. `Auto` colors only when stdout
+/// is a terminal and the `NO_COLOR` environment variable is unset or empty,
+/// following the <https://no-color.org> convention.
+///
+/// # Arguments
+///
+/// * `choice` - The value of the `--color` option
+///
+/// # Returns
+///
+/// Returns `true` if ANSI escape codes should be emitted.
+///
+/// # Examples
+///
+/// ```
+/// let color = use_color(ColorChoice::Auto);
+/// ```
+pub fn use_color(choice: ColorChoice) -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    color_enabled(choice, no_color, io::stdout().is_terminal())
+}
+
+/// Decide whether output should be colored, given the environment.
+///
+/// This holds the logic of `use_color`, which calls it with the `NO_COLOR`
+/// setting and the terminal status of stdout.
+fn color_enabled(choice: ColorChoice, no_color: bool, terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => !no_color && terminal,
+    }
+}
+
+/// Print tasks as a table.
+///
+/// Prints a header row followed by one line per task, including when each
+/// completed task was finished and its tags. Pending tasks whose due date
+/// has passed are flagged with an `(OVERDUE)` marker.
+///
+/// By default the table is drawn with box-drawing borders and every column
+/// is sized to its widest cell, measured in terminal columns so wide
+/// Unicode characters stay aligned. Descriptions wider than
+/// `options.max_width`, or than the room left in `options.terminal_width`,
+/// are truncated with an ellipsis, or wrapped onto extra lines with
+/// `options.wrap`. With `options.plain` the original fixed-width layout is
+/// printed instead.
+///
+/// When `options.color` is set, completed tasks are dim green, overdue
+/// tasks red and high-priority tasks bold. Only the cell contents are
+/// styled, never the padding, so columns stay aligned.
+///
+/// # Arguments
+///
+/// * `tasks` - The tasks to display
+/// * `options` - Table style and description width
+///
+/// # Examples
+///
+/// ```
+/// let tasks = Task::list(&conn, "default")?;
+/// let options = TableOptions {
+///     plain: false,
+///     max_width: 60,
+///     wrap: false,
+///     terminal_width: terminal_width(),
+///     color: true,
+///     date_format: TIMESTAMP_FORMAT.to_string(),
+///     utc: false,
+/// };
+/// print_task_table(&tasks, &options);
+/// ```
+pub fn print_task_table(tasks: &[Task], options: &TableOptions) {
+    let today = Local::now().date_naive();
+    let rows: Vec<[String; 8]> = tasks
+        .iter()
+        .map(|task| task_cells(task, today, options))
+        .collect();
+    let styles: Vec<Option<String>> = tasks
+        .iter()
+        .map(|task| row_style(task, today).filter(|_| options.color))
+        .collect();
+
+    if options.plain {
+        print_plain_table(&TABLE_HEADERS, &rows, &styles, &PLAIN_WIDTHS);
+    } else {
+        print_box_table(&TABLE_HEADERS, rows, &styles, options);
+    }
+}
+
+/// Print fuzzy search results as a table.
+///
+/// Works like [`print_task_table`], with an extra `SCORE` column in front
+/// so the relevance of each match can be judged.
+///
+/// # Arguments
+///
+/// * `matches` - The search results to display, best match first
+/// * `options` - Table style and description width
+///
+/// # Examples
+///
+/// ```
+/// let matches = Task::search_fuzzy(&conn, "default", "invc")?;
+/// print_match_table(&matches, &options);
+/// ```
+pub fn print_match_table(matches: &[SearchMatch], options: &TableOptions) {
+    let today = Local::now().date_naive();
+    let rows: Vec<[String; 9]> = matches
+        .iter()
+        .map(|found| match_cells(found, today, options))
+        .collect();
+    let styles: Vec<Option<String>> = matches
+        .iter()
+        .map(|found| row_style(&found.task, today).filter(|_| options.color))
+        .collect();
+
+    if options.plain {
+        print_plain_table(&MATCH_HEADERS, &rows, &styles, &MATCH_PLAIN_WIDTHS);
+    } else {
+        print_box_table(&MATCH_HEADERS, rows, &styles, options);
+    }
+}
+
+/// Print tasks as a GitHub-flavored Markdown table.
+///
+/// The columns match [`print_task_table`], but completed tasks are shown as
+/// `[x]` and pending ones as `[ ]`. Descriptions are printed in full, with
+/// pipes escaped and line breaks replaced by spaces so each task stays on
+/// a single row.
+///
+/// # Arguments
+///
+/// * `tasks` - The tasks to display
+/// * `options` - Table options supplying the date format
+///
+/// # Examples
+///
+/// ```
+/// let tasks = Task::list(&conn, "default")?;
+/// print_markdown_table(&tasks, &options);
+/// ```
+pub fn print_markdown_table(tasks: &[Task], options: &TableOptions) {
+    let today = Local::now().date_naive();
+
+    let rows = tasks.iter().map(|task| {
+        let mut cells = task_cells(task, today, options);
+        cells[1] = markdown_checkbox(task);
+        cells
+    });
+    print_markdown_rows(&TABLE_HEADERS, rows);
+}
+
+/// Print tasks as a GitHub-flavored Markdown task list.
+///
+/// Each task becomes a `- [ ] description` item, or `- [x] description`
+/// when it is done, in the given order. Subtasks from `Task::list_tree` are
+/// indented under their parent so they render as nested items. Line breaks
+/// in descriptions are replaced by spaces so every task stays one item.
+///
+/// # Arguments
+///
+/// * `tasks` - The tasks to print
+///
+/// # Examples
+///
+/// ```
+/// let tasks = Task::list_tree(&conn, "default", &ListOptions::default())?;
+/// print_checklist(&tasks);
+/// ```
+pub fn print_checklist<'a>(tasks: impl IntoIterator<Item = &'a Task>) {
+    for task in tasks {
+        let description: String = task
+            .description
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        println!(
+            "{}- {} {}",
+            "  ".repeat(task.depth),
+            markdown_checkbox(task),
+            description
+        );
+    }
+}
+
+/// Print fuzzy search results as a GitHub-flavored Markdown table.
+///
+/// The columns match [`print_match_table`], with the completion status
+/// shown as in [`print_markdown_table`].
+///
+/// # Arguments
+///
+/// * `matches` - The search results to display, best match first
+/// * `options` - Table options supplying the date format
+///
+/// # Examples
+///
+/// ```
+/// let matches = Task::search_fuzzy(&conn, "default", "invc")?;
+/// print_markdown_matches(&matches, &options);
+/// ```
+pub fn print_markdown_matches(matches: &[SearchMatch], options: &TableOptions) {
+    let today = Local::now().date_naive();
+
+    let rows = matches.iter().map(|found| {
+        let mut cells = match_cells(found, today, options);
+        cells[2] = markdown_checkbox(&found.task);
+        cells
+    });
+    print_markdown_rows(&MATCH_HEADERS, rows);
+}
+
+/// Print a header and rows as a Markdown table, escaping every cell.
+fn print_markdown_rows<const N: usize>(
+    headers: &[&str; N],
+    rows: impl Iterator<Item = [String; N]>,
+) {
+    println!("| {} |", headers.join(" | "));
+    println!("|{}", "---|".repeat(N));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| markdown_cell(cell)).collect();
+        println!("| {} |", cells.join(" | "));
+    }
+}
+
+/// Get the Markdown task list checkbox for a task's completion status.
+fn markdown_checkbox(task: &Task) -> String {
+    if task.done { "[x]" } else { "[ ]" }.to_string()
+}
+
+/// Print tasks as tab-separated lines, without a header.
+///
+/// Each line holds the ID, done status, priority, creation time, due date,
+/// completion time, comma-separated tags and description of one task, in
+/// that order. Missing values are empty fields, timestamps use the table's
+/// date format, and tabs, line breaks and backslashes in text are escaped
+/// so every task stays on one line with the same number of fields.
+///
+/// # Arguments
+///
+/// * `tasks` - The tasks to print
+/// * `options` - Table options supplying the date format
+///
+/// # Errors
+///
+/// Returns an error if stdout cannot be written, e.g. because the reading
+/// end of a pipe was closed. `main` exits quietly on the latter, so the
+/// output can be cut short with `head`.
+///
+/// # Examples
+///
+/// ```
+/// let tasks = Task::list(&conn, "default")?;
+/// print_tsv(&tasks, &options)?;
+/// ```
+pub fn print_tsv(tasks: &[Task], options: &TableOptions) -> Result<()> {
+    write_tsv(&mut io::stdout().lock(), tasks, options)
+}
+
+/// Write tasks as tab-separated lines.
+///
+/// This holds the logic of `print_tsv`, which calls it with stdout.
+fn write_tsv<W: Write>(output: &mut W, tasks: &[Task], options: &TableOptions) -> Result<()> {
+    for task in tasks {
+        writeln!(output, "{}", tsv_fields(task, options).join("\t"))
+            .context("Failed to write output")?;
+    }
+    Ok(())
+}
+
+/// Print fuzzy search results as tab-separated lines, without a header.
+///
+/// The fields match [`print_tsv`], preceded by the match score.
+///
+/// # Arguments
+///
+/// * `matches` - The search results to print, best match first
+/// * `options` - Table options supplying the date format
+///
+/// # Errors
+///
+/// Returns an error if stdout cannot be written, as [`print_tsv`] does.
+///
+/// # Examples
+///
+/// ```
+/// let matches = Task::search_fuzzy(&conn, "default", "invc")?;
+/// print_tsv_matches(&matches, &options)?;
+/// ```
+pub fn print_tsv_matches(matches: &[SearchMatch], options: &TableOptions) -> Result<()> {
+    let mut output = io::stdout().lock();
+    for found in matches {
+        let fields = tsv_fields(&found.task, options);
+        writeln!(output, "{}\t{}", found.score, fields.join("\t"))
+            .context("Failed to write output")?;
+    }
+    Ok(())
+}
+
+/// Render the tab-separated fields of a single task, in `TABLE_HEADERS` order.
+fn tsv_fields(task: &Task, options: &TableOptions) -> [String; 8] {
+    [
+        task.id.to_string(),
+        task.done.to_string(),
+        task.priority_label().to_string(),
+        format_time(task.birth, options),
+        task.due.map(|d| d.to_string()).unwrap_or_default(),
+        task.done_at
+            .map(|t| format_time(t, options))
+            .unwrap_or_default(),
+        tsv_field(&task.tags.join(",")),
+        tsv_field(&task.description),
+    ]
+}
+
+/// Escape backslashes, tabs and line breaks in a tab-separated field.
+fn tsv_field(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape a value for use inside a Markdown table cell.
+fn markdown_cell(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .replace('|', "\\|")
+}
+
+/// Print every field of a single task, one per line.
+///
+/// Unlike the task table, the description is printed in full, including
+/// any line breaks, followed by the task's notes, if it has any. Timestamps
+/// follow the table's date format and `utc` setting.
+///
+/// # Arguments
+///
+/// * `task` - The task to display
+/// * `options` - Table options supplying the date format
+///
+/// # Examples
+///
+/// ```
+/// if let Some(task) = Task::get(&conn, "default", 3)? {
+///     print_task_details(&task, &options);
+/// }
+/// ```
+pub fn print_task_details(task: &Task, options: &TableOptions) {
+    let today = Local::now().date_naive();
+    let [id, _, priority, birth, due, done_at, tags, _] = task_cells(task, today, options);
+    let status = match (task.done, task.archived) {
+        (true, true) => "done, archived",
+        (true, false) => "done",
+        (false, true) => "pending, archived",
+        (false, false) => "pending",
+    };
+
+    println!("ID:          {}", id);
+    println!("Status:      {}", status);
+    println!("Created:     {}", birth);
+    println!("Completed:   {}", done_at);
+    println!("Priority:    {}", priority);
+    println!("Due:         {}", due);
+    println!("Tags:        {}", tags);
+    if let Some(parent_id) = task.parent_id {
+        println!("Subtask of:  {}", parent_id);
+    }
+    if let Some(recur) = task.recur {
+        println!("Repeats:     {}", recur);
+    }
+    println!("Description: {}", task.description);
+
+    if let Some(notes) = &task.notes {
+        println!();
+        println!("Notes:");
+        for line in notes.lines() {
+            println!("  {}", line);
+        }
+    }
+}
+
+/// Get the ANSI style for a task's row, if it should be highlighted.
+fn row_style(task: &Task, today: NaiveDate) -> Option<String> {
+    let mut codes = Vec::new();
+
+    if task.done {
+        codes.push(DONE_STYLE);
+    } else if task.is_overdue(today) {
+        codes.push(OVERDUE_STYLE);
+    }
+    if task.priority == 3 {
+        codes.push(HIGH_PRIORITY_STYLE);
+    }
+
+    (!codes.is_empty()).then(|| codes.join(";"))
+}
+
+/// Wrap text in ANSI escape codes for the given style, if any.
+fn paint<'a>(text: &'a str, style: Option<&str>) -> Cow<'a, str> {
+    match style {
+        Some(style) => Cow::Owned(format!("\x1b[{}m{}\x1b[0m", style, text)),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// Style a cell and pad it to `width` display columns.
+///
+/// The padding is added after the escape codes so it is never styled and
+/// doesn't count toward the cell's visible width.
+fn pad_cell(cell: &str, width: usize, style: Option<&str>) -> String {
+    let padding = " ".repeat(width.saturating_sub(cell.width()));
+    format!("{}{}", paint(cell, style), padding)
+}
+
+/// Render the table cells for a single task, in `TABLE_HEADERS` order.
+fn task_cells(task: &Task, today: NaiveDate, options: &TableOptions) -> [String; 8] {
+    let due_display = match task.due {
+        Some(due) if task.is_overdue(today) => format!("{} (OVERDUE)", due),
+        Some(due) => due.to_string(),
+        None => "-".to_string(),
+    };
+    let done_at_display = task
+        .done_at
+        .map(|t| format_time(t, options))
+        .unwrap_or_else(|| "-".to_string());
+    let tags_display = if task.tags.is_empty() {
+        "-".to_string()
+    } else {
+        task.tags.join(",")
+    };
+
+    [
+        task.id.to_string(),
+        task.done.to_string(),
+        task.priority_label().to_string(),
+        format_time(task.birth, options),
+        due_display,
+        done_at_display,
+        tags_display,
+        indent_description(task),
+    ]
+}
+
+/// Render the table cells for a fuzzy search result, in `MATCH_HEADERS` order.
+fn match_cells(found: &SearchMatch, today: NaiveDate, options: &TableOptions) -> [String; 9] {
+    let [id, done, priority, birth, due, done_at, tags, description] =
+        task_cells(&found.task, today, options);
+    [
+        found.score.to_string(),
+        id,
+        done,
+        priority,
+        birth,
+        due,
+        done_at,
+        tags,
+        description,
+    ]
+}
+
+/// Get a task's description, indented below its parent for subtasks.
+fn indent_description(task: &Task) -> String {
+    if task.depth == 0 {
+        return task.description.clone();
+    }
+    format!("{}└ {}", "  ".repeat(task.depth - 1), task.description)
+}
+
+/// Format a task timestamp for display, converting it to UTC if requested.
+///
+/// Task timestamps are held in local time. A time that doesn't exist
+/// locally, such as one in a daylight saving gap, is shown unconverted.
+fn format_time(time: NaiveDateTime, options: &TableOptions) -> String {
+    let time = match Local.from_local_datetime(&time).earliest() {
+        Some(local) if options.utc => local.naive_utc(),
+        _ => time,
+    };
+    time.format(&options.date_format).to_string()
+}
+
+/// Print rows in the fixed-width `|`-separated layout.
+///
+/// `widths` holds the width of every column but the last, the description.
+fn print_plain_table<const N: usize>(
+    headers: &[&str; N],
+    rows: &[[String; N]],
+    styles: &[Option<String>],
+    widths: &[usize],
+) {
+    // Print header, with a rule as wide as the padded columns plus 16
+    println!("{}", plain_row(headers, None, widths));
+    println!(
+        "{}",
+        "-".repeat(widths.iter().map(|w| w + 3).sum::<usize>() + 16)
+    );
+
+    // Print each task
+    for (row, style) in rows.iter().zip(styles) {
+        println!("{}", plain_row(row, style.as_deref(), widths));
+    }
+}
+
+/// Build a plain table row, padding every cell but the last.
+fn plain_row<S: AsRef<str>, const N: usize>(
+    cells: &[S; N],
+    style: Option<&str>,
+    widths: &[usize],
+) -> String {
+    let mut line = String::new();
+    for (cell, &width) in cells.iter().zip(widths) {
+        line.push_str(&pad_cell(cell.as_ref(), width, style));
+        line.push_str(" | ");
+    }
+    line.push_str(&paint(cells[N - 1].as_ref(), style));
+    line
+}
+
+/// Print rows as a bordered table sized to its content.
+///
+/// The last column holds the description. It is limited to
+/// `options.max_width` and, when the terminal width is known, to the room
+/// the other columns leave on the line, but never narrower than its
+/// header. Longer descriptions are truncated, or wrapped onto continuation
+/// lines with empty cells in the other columns when `options.wrap` is set.
+fn print_box_table<const N: usize>(
+    headers: &[&str; N],
+    rows: Vec<[String; N]>,
+    styles: &[Option<String>],
+    options: &TableOptions,
+) {
+    let mut widths = headers.map(UnicodeWidthStr::width);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row).take(N - 1) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    // Borders and padding take 3 columns per cell plus 1 for the right edge
+    let used: usize = widths[..N - 1].iter().map(|w| w + 3).sum::<usize>() + 4;
+    let limit = options
+        .terminal_width
+        .map_or(options.max_width, |terminal| {
+            options.max_width.min(terminal.saturating_sub(used))
+        })
+        .max(widths[N - 1]);
+
+    let mut lines: Vec<([String; N], Option<&str>)> = Vec::new();
+    for (mut row, style) in rows.into_iter().zip(styles) {
+        // Line breaks would split a row across lines and break the borders
+        let description = row[N - 1].replace(|c: char| c.is_control(), " ");
+        if options.wrap {
+            let mut parts = wrap_to_width(&description, limit).into_iter();
+            row[N - 1] = parts.next().unwrap_or_default();
+            lines.push((row, style.as_deref()));
+            for part in parts {
+                let continuation = std::array::from_fn(|i| {
+                    if i == N - 1 {
+                        part.clone()
+                    } else {
+                        String::new()
+                    }
+                });
+                lines.push((continuation, style.as_deref()));
+            }
+        } else {
+            row[N - 1] = truncate_to_width(&description, limit);
+            lines.push((row, style.as_deref()));
+        }
+    }
+    for (line, _) in &lines {
+        widths[N - 1] = widths[N - 1].max(line[N - 1].width());
+    }
+
+    println!("{}", box_border(&widths, '┌', '┬', '┐'));
+    println!("{}", box_row(headers, &widths, None));
+    println!("{}", box_border(&widths, '├', '┼', '┤'));
+    for (line, style) in &lines {
+        println!("{}", box_row(line, &widths, *style));
+    }
+    println!("{}", box_border(&widths, '└', '┴', '┘'));
+}
+
+/// Build a horizontal border line using the given corner and junction characters.
+fn box_border(widths: &[usize], left: char, middle: char, right: char) -> String {
+    let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+    format!("{}{}{}", left, segments.join(&middle.to_string()), right)
+}
+
+/// Build a table row, padding each cell to its column's display width.
+fn box_row<S: AsRef<str>>(cells: &[S], widths: &[usize], style: Option<&str>) -> String {
+    let padded: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, &width)| pad_cell(cell.as_ref(), width, style))
+        .collect();
+    format!("│ {} │", padded.join(" │ "))
+}
+
+/// Shorten text to at most `max_width` terminal columns.
+///
+/// Text that fits is returned unchanged; otherwise it is cut at a character
+/// boundary and an ellipsis is appended, keeping the total within the limit.
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        width += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Split text into lines of at most `max_width` terminal columns.
+///
+/// Lines are broken at spaces where possible; a word wider than a whole
+/// line is split at character boundaries. Always returns at least one line.
+fn wrap_to_width(text: &str, max_width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+
+    for word in text.split(' ') {
+        let separator = usize::from(!line.is_empty());
+        let word_width = word.width();
+        if line_width + separator + word_width <= max_width {
+            if separator > 0 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            line_width += separator + word_width;
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+        for c in word.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if line_width + char_width > max_width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            line.push(c);
+            line_width += char_width;
+        }
+    }
+    lines.push(line);
+
+    lines
+}
+
+/// Render a textual progress bar such as `[#####-----] 50%`.
+///
+/// The bar has `width` cells between the brackets, and both the filled
+/// cells and the percentage are rounded down, so the bar only shows as full
+/// once everything is done. A `total` of zero renders an empty bar at 0%.
+///
+/// # Arguments
+///
+/// * `done` - How many items are done
+/// * `total` - How many items there are in all
+/// * `width` - Number of cells in the bar
+///
+/// # Returns
+///
+/// Returns the bar followed by the percentage.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(render_bar(5, 10, 10), "[#####-----] 50%");
+/// assert_eq!(render_bar(0, 0, 4), "[----] 0%");
+/// ```
+pub fn render_bar(done: usize, total: usize, width: usize) -> String {
+    let done = done.min(total);
+    let filled = (done * width).checked_div(total).unwrap_or(0);
+    let percent = (done * 100).checked_div(total).unwrap_or(0);
+
+    format!(
+        "[{}{}] {}%",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        percent
+    )
+}
+
+/// Print a value as pretty-printed JSON.
+///
+/// Used for the `--format json` output mode so scripts can consume task
+/// data without parsing the human-readable table.
+///
+/// # Arguments
+///
+/// * `value` - Any serializable value, typically a slice of tasks
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized or stdout cannot be
+/// written, e.g. because the reading end of a pipe was closed. `main`
+/// exits quietly on the latter.
+///
+/// # Examples
+///
+/// ```
+/// let tasks = Task::list(&conn, "default")?;
+/// print_json(&tasks)?;
+/// ```
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    write_json(&mut io::stdout().lock(), value)
+}
+
+/// Write a value as pretty-printed JSON, followed by a newline.
+///
+/// This holds the logic of `print_json`, which calls it with stdout.
+fn write_json<W: Write, T: Serialize + ?Sized>(output: &mut W, value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).context("Failed to serialize output as JSON")?;
+    writeln!(output, "{}", json).context("Failed to write output")?;
+    Ok(())
+}
+
+/// Encode a single CSV field.
+///
+/// Fields containing commas, double quotes or line breaks are wrapped in
+/// double quotes, with embedded quotes doubled as described in RFC 4180.
+/// All other fields are returned unchanged.
+///
+/// # Arguments
+///
+/// * `value` - The raw field value
+///
+/// # Returns
+///
+/// Returns the field ready to be written to a CSV row.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(csv_field("plain"), "plain");
+/// assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
+/// ```
+pub fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Parse CSV text into records.
+///
+/// Supports the RFC 4180 conventions written by `csv_field`: fields may be
+/// wrapped in double quotes, in which case they can contain commas, line
+/// breaks and doubled `""` quotes. Both `\n` and `\r\n` line endings are
+/// accepted and blank lines are skipped.
+///
+/// # Arguments
+///
+/// * `input` - The full CSV text
+///
+/// # Returns
+///
+/// Returns one `Vec<String>` of fields per record, in input order.
+///
+/// # Errors
+///
+/// Returns an error if a quoted field is not terminated.
+///
+/// # Examples
+///
+/// ```
+/// let records = parse_csv("id,description\n1,\"a, b\"\n")?;
+/// assert_eq!(records[1], vec!["1", "a, b"]);
+/// ```
+pub fn parse_csv(input: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
             continue;
         }
 
-        match input.trim().to_ascii_uppercase().as_str() {
-            "Y" => return true,
-            "N" => return false,
-            _ => {
-                println!("Please enter 'Y' or 'N'.");
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if !record.is_empty() || !field.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
             }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        bail!("Unterminated quoted field in CSV input");
+    }
+
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::with_env;
+
+    /// A writer whose reader has gone away, like stdout piped into `head`.
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_json_writes_pretty_json() {
+        let mut output = Vec::new();
+
+        write_json(&mut output, &serde_json::json!({ "pending": 2 })).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\n  \"pending\": 2\n}\n"
+        );
+    }
+
+    #[test]
+    fn write_json_reports_a_closed_pipe() {
+        let err = write_json(&mut ClosedPipe, &[1, 2, 3]).unwrap_err();
+
+        assert!(crate::is_broken_pipe(&err));
+    }
+
+    /// Run `read_confirmation` on scripted input.
+    ///
+    /// Returns the answer and everything written to the output.
+    fn confirm_with(input: &str, default: Option<bool>) -> (bool, String) {
+        let mut output = Vec::new();
+        let answer =
+            read_confirmation(&mut input.as_bytes(), &mut output, "Continue?", default).unwrap();
+        (answer, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn confirmation_accepts_yes() {
+        assert_eq!(
+            confirm_with("y\n", None),
+            (true, "Continue? (Y/N): ".to_string())
+        );
+        assert!(confirm_with("Y\n", None).0);
+    }
+
+    #[test]
+    fn confirmation_accepts_no() {
+        assert_eq!(
+            confirm_with("N\n", None),
+            (false, "Continue? (Y/N): ".to_string())
+        );
+        assert!(!confirm_with("n\n", None).0);
+    }
+
+    #[test]
+    fn confirmation_prompts_again_after_invalid_input() {
+        let (answer, output) = confirm_with("maybe\n  y  \n", None);
+
+        assert!(answer);
+        assert_eq!(
+            output,
+            "Continue? (Y/N): Please enter 'Y' or 'N'.\nContinue? (Y/N): "
+        );
+    }
+
+    #[test]
+    fn confirmation_declines_at_end_of_input() {
+        assert_eq!(
+            confirm_with("", None),
+            (false, "Continue? (Y/N): \n".to_string())
+        );
+    }
+
+    #[test]
+    fn confirmation_uses_the_default_for_an_empty_line() {
+        assert_eq!(
+            confirm_with("\n", Some(true)),
+            (true, "Continue? (Y/n): ".to_string())
+        );
+        assert_eq!(
+            confirm_with("\n", Some(false)),
+            (false, "Continue? (y/N): ".to_string())
+        );
+    }
+
+    #[test]
+    fn confirmation_answer_overrides_the_default() {
+        assert!(!confirm_with("n\n", Some(true)).0);
+        assert!(confirm_with("y\n", Some(false)).0);
+    }
+
+    #[test]
+    fn confirmation_without_default_rejects_an_empty_line() {
+        let (answer, output) = confirm_with("\ny\n", None);
+
+        assert!(answer);
+        assert_eq!(
+            output,
+            "Continue? (Y/N): Please enter 'Y' or 'N'.\nContinue? (Y/N): "
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit_in_editor_uses_a_private_temporary_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // The editor records the file it was given and its permissions,
+        // then replaces the text
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = dir.path().join("editor.log");
+        let editor = dir.path().join("editor.sh");
+        std::fs::write(
+            &editor,
+            format!(
+                "#!/bin/sh\nls -l \"$1\" | cut -c1-10 > '{log}'\necho \"$1\" >> '{log}'\n\
+                 echo 'Edited' > \"$1\"\n",
+                log = log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&editor, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let edited = with_env(&[("EDITOR", editor.to_str())], || {
+            edit_in_editor("Original").unwrap()
+        });
+
+        assert_eq!(edited, "Edited");
+        let log = std::fs::read_to_string(&log).unwrap();
+        let (mode, path) = log.trim().split_once('\n').unwrap();
+        assert_eq!(mode, "-rw-------");
+
+        let path = std::path::Path::new(path);
+        let name = path.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("todo-rs-edit-"), "{}", name);
+        assert_ne!(name, format!("todo-rs-edit-{}.txt", std::process::id()));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn confirmation_stops_prompting_when_input_is_closed() {
+        // `echo | todo-rs clear` sends one empty line and then closes stdin,
+        // which used to make the prompt repeat forever
+        let (answer, output) = confirm_with("\n", None);
+
+        assert!(!answer);
+        assert_eq!(
+            output,
+            "Continue? (Y/N): Please enter 'Y' or 'N'.\nContinue? (Y/N): \n"
+        );
+    }
+
+    #[test]
+    fn confirmation_returns_the_default_when_input_is_closed() {
+        assert!(confirm_with("", Some(true)).0);
+        assert!(!confirm_with("", Some(false)).0);
+    }
+
+    #[test]
+    fn no_color_disables_automatic_color() {
+        assert!(color_enabled(ColorChoice::Auto, false, true));
+        assert!(!color_enabled(ColorChoice::Auto, true, true));
+        assert!(!color_enabled(ColorChoice::Auto, false, false));
+    }
+
+    #[test]
+    fn explicit_color_choice_overrides_no_color() {
+        assert!(color_enabled(ColorChoice::Always, true, false));
+        assert!(!color_enabled(ColorChoice::Never, false, true));
+    }
+
+    #[test]
+    fn use_color_reads_no_color_from_the_environment() {
+        with_env(&[("NO_COLOR", Some("1"))], || {
+            assert!(!use_color(ColorChoice::Auto));
+            assert!(use_color(ColorChoice::Always));
+        });
+    }
+
+    fn tsv_options() -> TableOptions {
+        TableOptions {
+            plain: false,
+            max_width: 60,
+            wrap: false,
+            terminal_width: None,
+            color: false,
+            date_format: crate::models::TIMESTAMP_FORMAT.to_string(),
+            utc: false,
         }
     }
+
+    fn tsv_task(id: i64, description: &str) -> Task {
+        Task {
+            id,
+            description: description.to_string(),
+            done: false,
+            birth: NaiveDate::from_ymd_opt(2024, 12, 7)
+                .unwrap()
+                .and_hms_opt(14, 30, 15)
+                .unwrap(),
+            priority: 2,
+            due: NaiveDate::from_ymd_opt(2024, 12, 24),
+            done_at: None,
+            tags: vec!["home".to_string(), "work".to_string()],
+            notes: None,
+            recur: None,
+            parent_id: None,
+            archived: false,
+            depth: 0,
+        }
+    }
+
+    #[test]
+    fn write_tsv_writes_one_escaped_line_per_task() {
+        let tasks = [tsv_task(1, "Pay rent"), tsv_task(2, "Tab\there\nand \\")];
+        let mut output = Vec::new();
+
+        write_tsv(&mut output, &tasks, &tsv_options()).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1\tfalse\tmedium\t2024-12-07 14:30:15\t2024-12-24\t\thome,work\tPay rent\n\
+             2\tfalse\tmedium\t2024-12-07 14:30:15\t2024-12-24\t\thome,work\tTab\\there\\nand \\\\\n"
+        );
+    }
+
+    #[test]
+    fn write_tsv_reports_a_closed_pipe() {
+        let err =
+            write_tsv(&mut ClosedPipe, &[tsv_task(1, "Pay rent")], &tsv_options()).unwrap_err();
+
+        assert!(crate::is_broken_pipe(&err));
+    }
+
+    #[test]
+    fn render_bar_rounds_down() {
+        assert_eq!(render_bar(5, 10, 10), "[#####-----] 50%");
+        assert_eq!(render_bar(2, 7, 20), "[#####---------------] 28%");
+        // Almost done still isn't a full bar
+        assert_eq!(render_bar(199, 200, 10), "[#########-] 99%");
+        assert_eq!(render_bar(3, 3, 4), "[####] 100%");
+    }
+
+    #[test]
+    fn render_bar_handles_no_tasks() {
+        assert_eq!(render_bar(0, 0, 4), "[----] 0%");
+    }
+
+    #[test]
+    fn render_bar_caps_done_at_the_total() {
+        assert_eq!(render_bar(12, 10, 5), "[#####] 100%");
+    }
 }
@@ -5,7 +5,7 @@
 //! ## Overview
 //! 
 //! todo-rs is a lightweight CLI application for managing daily tasks. It provides
-//! a clean, intuitive interface for adding, listing, completing, and removing tasks.
+//! a clean, intuitive interface for adding, listing, editing, completing, and removing tasks.
 //! All data is stored locally in a SQLite database within your system's config directory.
 //! 
 //! ## Quick Start
@@ -20,6 +20,9 @@
 //! # Mark task as done
 //! todo-rs done 1
 //! 
+//! # Fix a task's description
+//! todo-rs edit 1 "Buy groceries and milk"
+//! 
 //! # Remove a task
 //! todo-rs remove 1
 //! ```
@@ -39,11 +42,21 @@
 //! The application uses [`anyhow`] for comprehensive error handling, providing
 //! clear error messages and proper error propagation throughout the codebase.
 //! 
+//! ## Exit codes
+//! 
+//! - `0`: Success
+//! - `1`: Any other error
+//! - `2`: Invalid command line usage
+//! - `3`: No matching task, e.g. `remove`, `done`, `undone`, `edit`, `move` or `show`
+//!   with an ID that doesn't exist or a task already in the requested state
+//! 
 //! ## Storage
 //! 
 //! Tasks are stored in a SQLite database located in your system's config directory:
-//! - Linux/macOS: `~/.config/todo-rs/tasks.db`
-//! - Respects `XDG_CONFIG_HOME` environment variable when set
+//! - Linux: `~/.config/todo-rs/tasks.db`
+//!   (respects `XDG_CONFIG_HOME` environment variable when set)
+//! - macOS: `~/Library/Application Support/todo-rs/tasks.db`
+//! - Windows: `%APPDATA%\todo-rs\tasks.db`
 //! 
 //! ## Examples
 //! 
@@ -58,11 +71,13 @@
 //! # List all tasks
 //! todo-rs list
 //! # Output:
-//! # ID       | DONE     | BIRTH               | DESCRIPTION
-//! # ------------------------------------------------------------
-//! # 1        | false    | 2024-12-07 14:30:15 | Write documentation
-//! # 2        | false    | 2024-12-07 14:30:22 | Review pull requests
-//! # 3        | false    | 2024-12-07 14:30:25 | Deploy to production
+//! # ┌────┬───────┬──────────┬─────────────────────┬─────┬─────────┬──────┬──────────────────────┐
+//! # │ ID │ DONE  │ PRIORITY │ BIRTH               │ DUE │ DONE AT │ TAGS │ DESCRIPTION          │
+//! # ├────┼───────┼──────────┼─────────────────────┼─────┼─────────┼──────┼──────────────────────┤
+//! # │ 1  │ false │ none     │ 2024-12-07 14:30:15 │ -   │ -       │ -    │ Write documentation  │
+//! # │ 2  │ false │ none     │ 2024-12-07 14:30:22 │ -   │ -       │ -    │ Review pull requests │
+//! # │ 3  │ false │ none     │ 2024-12-07 14:30:25 │ -   │ -       │ -    │ Deploy to production │
+//! # └────┴───────┴──────────┴─────────────────────┴─────┴─────────┴──────┴──────────────────────┘
 //! 
 //! # Complete a task
 //! todo-rs done 1
@@ -6,39 +6,131 @@
 //! - Adding new tasks
 //! - Listing all tasks with their status
 //! - Marking tasks as complete
+//! - Editing task descriptions
 //! - Removing tasks
 //!
 //! Tasks are stored in a SQLite database located in the user's config directory,
 //! following XDG Base Directory specification when available.
+//!
+//! ## Exit codes
+//!
+//! - `0`: Success
+//! - `1`: Any other error
+//! - `2`: Invalid command line usage (reported by clap)
+//! - `3`: No matching task, e.g. `remove`, `done`, `undone`, `edit`, `move` or `show`
+//!   with an ID that doesn't exist or a task already in the requested state
+//!
+//! With `--format json` (given on the command line or in the config file),
+//! errors are written to stderr as a JSON object holding the message and the
+//! exit code, e.g. `{"code":3,"error":"No task found with id: 42"}`.
 
 use anyhow::Result;
+use std::io;
+use std::path::Path;
 use std::process::exit;
+use std::time::Instant;
 
 mod args;
 mod config;
 mod database;
 mod io_utils;
 mod models;
+mod recur;
+
+/// Exit status for errors without a more specific code.
+const EXIT_ERROR: i32 = 1;
+
+/// Exit status when a command names a task that doesn't exist.
+const EXIT_NO_MATCHING_TASK: i32 = 3;
 
 /// Main entry point for the todo-rs application.
 ///
 /// This function handles the overall application flow and error handling.
 /// If any error occurs during execution, it will be printed to stderr
-/// and the program will exit with status code 1.
+/// and the program will exit with status code `EXIT_NO_MATCHING_TASK` when
+/// a command named a missing task, or `EXIT_ERROR` otherwise. Output cut
+/// short by a closed pipe is not an error and exits with status 0. When JSON
+/// output is selected the error is printed as a JSON object with `error`
+/// and `code` fields instead of as text.
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        exit(1);
+    let cli = args::parse_args();
+    let mut json_errors = cli.format == Some(args::OutputFormat::Json);
+
+    if let Err(e) = run(cli, &mut json_errors) {
+        // The reader went away, e.g. `todo-rs --format json list | head -1`;
+        // there is nobody left to tell, so stop quietly like other tools do
+        if is_broken_pipe(&e) {
+            exit(0);
+        }
+
+        let code = if e.downcast_ref::<database::NoMatchingTask>().is_some() {
+            EXIT_NO_MATCHING_TASK
+        } else {
+            EXIT_ERROR
+        };
+
+        if json_errors {
+            eprintln!(
+                "{}",
+                serde_json::json!({ "error": format!("{:#}", e), "code": code })
+            );
+        } else {
+            eprintln!("Error: {:#}", e);
+        }
+        exit(code);
+    }
+}
+
+/// Check whether an error was caused by writing to a closed pipe.
+///
+/// # Arguments
+///
+/// * `error` - The error returned by `run`
+///
+/// # Returns
+///
+/// Returns `true` if any error in the chain is an `io::Error` of kind
+/// `BrokenPipe`.
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+/// Remove a database file that failed to initialize.
+///
+/// SQLite's `-wal` and `-shm` files next to it are removed too, so a stale
+/// WAL can't be replayed into the next database created at the same path.
+/// Files that don't exist are skipped, and other errors are ignored since
+/// the initialization error is the one worth reporting.
+///
+/// # Arguments
+///
+/// * `db_path` - Path of the database file
+fn remove_partial_database(db_path: &Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = db_path.as_os_str().to_owned();
+        file.push(suffix);
+        let _ = std::fs::remove_file(file);
     }
 }
 
 /// Core application logic.
 ///
 /// This function orchestrates the main application flow:
-/// 1. Parse command line arguments
-/// 2. Ensure config directory exists and is writable  
-/// 3. Check if database exists, create if needed with user confirmation
-/// 4. Execute the requested database operation
+/// 1. Load the config file and resolve the output format
+/// 2. Resolve the database path (`--db`, then `TODO_RS_DB`, then the default location)
+/// 3. Ensure config directory exists and is writable when the default location is used
+/// 4. Check if database exists, create and initialize it if needed with user confirmation
+/// 5. Execute the requested database operation
+///
+/// # Arguments
+///
+/// * `cli` - The parsed command line arguments
+/// * `json_errors` - Set to whether errors should be reported as JSON, once
+///   the config file has been read
 ///
 /// # Returns
 ///
@@ -47,30 +139,163 @@ fn main() {
 /// # Errors
 ///
 /// This function can return errors for:
-/// - Invalid command line arguments
 /// - Config directory creation/permission issues
 /// - Database creation/access problems
 /// - Task operation failures
-fn run() -> Result<()> {
-    let command = args::parse_args();
+fn run(cli: args::Cli, json_errors: &mut bool) -> Result<()> {
+    // Completions need neither the config file nor the database
+    if let args::Commands::Completions { shell } = cli.command {
+        args::print_completions(shell);
+        return Ok(());
+    }
 
-    config::ensure_config_dir()?;
-    config::check_config_dir_writable()?;
+    let started = Instant::now();
+    let settings = config::load_config(cli.verbose)?;
+    let format = cli.format.or(settings.format).unwrap_or_default();
+    *json_errors = format == args::OutputFormat::Json;
 
-    let db_path = database::get_db_path()?;
+    // The config directory is only needed when the default database is used
+    let db_path = match database::custom_db_path(cli.db.as_deref())? {
+        Some(path) => path,
+        None => {
+            config::ensure_config_dir(cli.quiet)?;
+            config::check_config_dir_writable()?;
+            database::get_db_path()?
+        }
+    };
+    if cli.verbose {
+        eprintln!("Using database {}", db_path.display());
+    }
 
     if !database::check_db_exists(&db_path) {
-        println!("Database not found at {}", db_path.display());
+        if !cli.quiet {
+            println!("Database not found at {}", db_path.display());
+        }
 
-        if io_utils::ask_user_confirmation("Do you want to create it? (Y/N): ") {
-            database::create_database(&db_path)?;
-            println!("Database created at {}", db_path.display());
+        if io_utils::confirm("Do you want to create it?", Some(true), cli.yes)? {
+            // Don't leave a half-initialized file behind for the next run to trip over
+            if let Err(err) = database::create_database(&db_path) {
+                remove_partial_database(&db_path);
+                return Err(err);
+            }
+            if !cli.quiet {
+                println!("Database created at {}", db_path.display());
+            }
         } else {
-            println!("Goodbye!");
+            if !cli.quiet {
+                println!("Goodbye!");
+            }
             return Ok(());
         }
     }
 
-    database::handle_db_operations(&db_path, command)?;
+    let table = io_utils::TableOptions {
+        plain: cli.plain,
+        max_width: cli.max_width.into(),
+        wrap: cli.wrap,
+        terminal_width: io_utils::terminal_width(),
+        color: io_utils::use_color(
+            cli.color
+                .or(settings.color)
+                .unwrap_or(args::ColorChoice::Auto),
+        ),
+        date_format: cli
+            .date_format
+            .or_else(|| settings.date_format.clone())
+            .unwrap_or_else(|| models::TIMESTAMP_FORMAT.to_string()),
+        utc: cli.utc,
+    };
+    database::handle_db_operations(
+        &db_path,
+        cli.command,
+        &cli.profile,
+        format,
+        table,
+        &settings,
+        cli.yes,
+        cli.quiet,
+        cli.verbose,
+    )?;
+
+    if cli.verbose {
+        eprintln!(
+            "Finished in {:.1} ms",
+            started.elapsed().as_secs_f64() * 1000.0
+        );
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use tempfile::TempDir;
+
+    use crate::config::tests::with_env;
+
+    /// Run the application with the given arguments after the program name.
+    fn run_with(args: &[&str]) -> Result<()> {
+        let cli =
+            args::Cli::try_parse_from(std::iter::once("todo-rs").chain(args.iter().copied()))?;
+        run(cli, &mut false)
+    }
+
+    #[test]
+    fn db_flag_works_without_home() {
+        let dir = TempDir::new().unwrap();
+        let db = dir.path().join("tasks.db");
+        let db = db.to_str().unwrap();
+        let vars = [
+            ("HOME", None),
+            ("XDG_CONFIG_HOME", None),
+            (database::DB_PATH_ENV, None),
+        ];
+
+        with_env(&vars, || {
+            run_with(&["--db", db, "--yes", "--quiet", "add", "Task"]).unwrap();
+            run_with(&["--db", db, "--quiet", "count"]).unwrap();
+        });
+
+        let conn = database::open_connection(std::path::Path::new(db)).unwrap();
+        assert_eq!(models::Task::list(&conn, "default").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn db_flag_does_not_create_the_config_dir() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config");
+        let db = dir.path().join("tasks.db");
+        let vars = [
+            ("HOME", None),
+            ("XDG_CONFIG_HOME", config.to_str()),
+            (database::DB_PATH_ENV, db.to_str()),
+        ];
+
+        with_env(&vars, || {
+            run_with(&["--yes", "--quiet", "add", "Task"]).unwrap();
+        });
+
+        assert!(db.exists());
+        assert!(!config.join("todo-rs").exists());
+    }
+
+    #[test]
+    fn remove_partial_database_removes_the_wal_files() {
+        let dir = TempDir::new().unwrap();
+        let db = dir.path().join("tasks.db");
+        let other = dir.path().join("other.db-wal");
+        for file in ["tasks.db", "tasks.db-wal", "tasks.db-shm", "other.db-wal"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        remove_partial_database(&db);
+
+        let left: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(left.len(), 1);
+        assert!(other.exists());
+
+        // Nothing left to remove is fine too
+        remove_partial_database(&db);
+    }
+}
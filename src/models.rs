@@ -3,9 +3,71 @@
 //! This module defines the core data structures and provides methods for
 //! interacting with the SQLite database.
 
-use anyhow::Result;
-use chrono::{Local, NaiveDateTime};
-use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OptionalExtension, Params};
+use serde::{Deserialize, Serialize};
+
+use crate::args::SortKey;
+use crate::recur::Recurrence;
+
+/// Format used to display local task timestamps such as `birth`.
+///
+/// Older databases, and ones with `utc_timestamps = false` in the config
+/// file, also store timestamps in this format. Such values carry no
+/// timezone and are read as local time.
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Format used to store task timestamps, in UTC as RFC 3339.
+///
+/// The trailing `Z` tells these values apart from ones stored in
+/// `TIMESTAMP_FORMAT`.
+pub const UTC_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Format used to store and parse calendar dates such as `due`.
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Longest task description, in characters, accepted by `add` and `edit`
+/// unless `max_description_length` is set in the config file.
+pub const DEFAULT_MAX_DESCRIPTION_LENGTH: usize = 1000;
+
+/// Check that a chrono format string can be used to display timestamps.
+///
+/// Display formats are user-supplied, and formatting with an invalid
+/// specifier fails at render time, so they are validated up front.
+///
+/// # Arguments
+///
+/// * `format` - A chrono `strftime`-style format string
+///
+/// # Returns
+///
+/// Returns `Ok(())` if every specifier in the format is recognized.
+///
+/// # Errors
+///
+/// Returns an error if the format contains an unknown or incomplete specifier.
+///
+/// # Examples
+///
+/// ```
+/// validate_date_format("%d/%m/%Y %H:%M")?;
+/// assert!(validate_date_format("%Q").is_err());
+/// ```
+pub fn validate_date_format(format: &str) -> Result<()> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        bail!("'{}' is not a valid date format", format);
+    }
+
+    Ok(())
+}
 
 /// Represents a task in the todo application.
 ///
@@ -19,43 +81,227 @@ use rusqlite::Connection;
 /// * `description` - Human-readable description of the task
 /// * `done` - Boolean indicating if the task is completed
 /// * `birth` - Timestamp when the task was created
-#[derive(Debug)]
+/// * `priority` - Priority level (0=none, 1=low, 2=medium, 3=high)
+/// * `due` - Optional date by which the task should be completed
+/// * `done_at` - Timestamp when the task was completed, if it is done
+/// * `tags` - Tags attached to the task, sorted alphabetically
+/// * `notes` - Optional free-form notes, possibly spanning several lines
+/// * `recur` - How often the task comes back once completed, if it repeats
+/// * `parent_id` - ID of the task this one is a subtask of, if any
+/// * `archived` - Whether the task is archived and hidden from listings
+/// * `depth` - Nesting level in a listing built by `Task::list_tree`, 0 for
+///   top-level tasks and everywhere else; not stored or serialized
+///
+/// Tasks serialize to JSON with `birth`, `due` and `done_at` as ISO-8601 strings.
+#[derive(Debug, Serialize)]
 pub struct Task {
     pub id: i64,
     pub description: String,
     pub done: bool,
     pub birth: NaiveDateTime,
+    pub priority: u8,
+    pub due: Option<NaiveDate>,
+    pub done_at: Option<NaiveDateTime>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    pub recur: Option<Recurrence>,
+    pub parent_id: Option<i64>,
+    pub archived: bool,
+    #[serde(skip)]
+    pub depth: usize,
 }
 
-impl Task {
-    /// Create the tasks table in the database if it doesn't exist.
+/// Options controlling which tasks a listing returns and in what order.
+///
+/// The default options return every task of the profile that isn't
+/// archived, in the manual order set with `move`.
+///
+/// # Fields
+///
+/// * `done` - Only return tasks with this completion status, if set
+/// * `sort` - Column to sort by
+/// * `reverse` - Sort in descending instead of ascending order
+/// * `tag` - Only return tasks carrying this tag, if set
+/// * `since` - Only return tasks created on or after this day, if set
+/// * `until` - Only return tasks created on or before this day, if set
+/// * `created_before` - Only return tasks created before this local time, if set
+/// * `limit` - Return at most this many tasks, if set
+/// * `offset` - Skip this many tasks before returning any, if set
+/// * `archived` - Return archived tasks instead of the other ones
+#[derive(Debug, Default, Clone)]
+pub struct ListOptions {
+    pub done: Option<bool>,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub tag: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub created_before: Option<NaiveDateTime>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub archived: bool,
+}
+
+impl SortKey {
+    /// Get the SQL expression used to order tasks by this key.
     ///
-    /// This method sets up the initial database schema. It's designed to be
-    /// idempotent - calling it multiple times won't cause errors.
+    /// # Returns
     ///
-    /// # Arguments
+    /// Returns a fixed column expression; never derived from user input.
+    pub fn column(self) -> &'static str {
+        match self {
+            SortKey::Position => "position",
+            SortKey::Id => "id",
+            // Compare as local time so UTC and local timestamps interleave
+            SortKey::Birth => {
+                "CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END"
+            }
+            SortKey::Done => "done",
+            SortKey::Description => "description COLLATE NOCASE",
+        }
+    }
+}
+
+/// Summary counts for the whole task list.
+///
+/// # Fields
+///
+/// * `total` - Number of tasks
+/// * `done` - Number of completed tasks
+/// * `pending` - Number of tasks not done yet
+/// * `completion_percentage` - Share of completed tasks, 0-100 (0 when there are no tasks)
+/// * `oldest_pending_days` - Age in whole days of the oldest pending task, if any
+/// * `created_last_7_days` - Number of tasks created in the last 7 days
+#[derive(Debug, Serialize)]
+pub struct TaskStats {
+    pub total: usize,
+    pub done: usize,
+    pub pending: usize,
+    pub completion_percentage: f64,
+    pub oldest_pending_days: Option<i64>,
+    pub created_last_7_days: usize,
+}
+
+/// Minimum fuzzy match score per character of the search term.
+///
+/// A matched character scores 16 before gap penalties and bonuses, so this
+/// drops matches whose characters are scattered across the description
+/// without landing on word starts.
+const FUZZY_MIN_SCORE_PER_CHAR: i64 = 16;
+
+/// A task found by a fuzzy search, with how well it matched.
+///
+/// # Fields
+///
+/// * `score` - Match score, higher is better
+/// * `task` - The matching task; its fields are serialized alongside `score`
+#[derive(Debug, Serialize)]
+pub struct SearchMatch {
+    pub score: i64,
+    #[serde(flatten)]
+    pub task: Task,
+}
+
+/// Tasks completed on a single day, as shown by the `history` command.
+///
+/// # Fields
+///
+/// * `date` - The completion date
+/// * `count` - Number of tasks completed that day
+/// * `tasks` - The completed tasks, in completion order
+#[derive(Debug, Serialize)]
+pub struct CompletionDay {
+    pub date: NaiveDate,
+    pub count: usize,
+    pub tasks: Vec<Task>,
+}
+
+impl CompletionDay {
+    /// Group completed tasks by the date they were completed.
     ///
-    /// * `conn` - SQLite database connection
+    /// Tasks without a completion time are skipped. The input is expected in
+    /// completion order, as returned by `Task::completed_between`.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// Returns `Ok(())` if the table was created or already exists.
+    /// * `tasks` - Completed tasks ordered by completion time
     ///
-    /// # Errors
+    /// # Returns
     ///
-    /// Returns an error if the SQL execution fails.
+    /// Returns one entry per day with at least one completion, most recent
+    /// day first.
     ///
     /// # Examples
     ///
     /// ```
-    /// let conn = Connection::open("tasks.db")?;
-    /// Task::create_default(&conn)?;
+    /// let tasks = Task::completed_between(&conn, "default", start, end)?;
+    /// for day in CompletionDay::group(tasks) {
+    ///     println!("{}: {}", day.date, day.count);
+    /// }
     /// ```
-    pub fn create_default(conn: &Connection) -> Result<()> {
-        conn.execute(crate::database::CREATE_TASK_TABLE, [])?;
-        Ok(())
+    pub fn group(tasks: Vec<Task>) -> Vec<CompletionDay> {
+        let mut days: Vec<CompletionDay> = Vec::new();
+
+        for task in tasks {
+            let Some(date) = task.done_at.map(|t| t.date()) else {
+                continue;
+            };
+
+            match days.last_mut() {
+                Some(day) if day.date == date => {
+                    day.count += 1;
+                    day.tasks.push(task);
+                }
+                _ => days.push(CompletionDay {
+                    date,
+                    count: 1,
+                    tasks: vec![task],
+                }),
+            }
+        }
+
+        days.reverse();
+        days
     }
+}
+
+/// A task record read from an import file.
+///
+/// Imported records never carry an ID; the database assigns a fresh one so
+/// they can't collide with existing tasks. Missing optional fields fall back
+/// to the same defaults used by `Task::add`.
+///
+/// # Fields
+///
+/// * `description` - Human-readable description of the task
+/// * `done` - Completion status, defaults to `false`
+/// * `birth` - Creation timestamp, defaults to the time of import
+/// * `priority` - Priority level, defaults to 0 (none)
+/// * `due` - Optional due date
+/// * `done_at` - Optional completion timestamp, only kept for done tasks
+/// * `tags` - Tags to attach to the task, defaults to none
+/// * `notes` - Optional free-form notes
+/// * `recur` - Optional recurrence interval
+/// * `archived` - Whether the task is archived, defaults to `false`
+#[derive(Debug, Deserialize)]
+pub struct ImportedTask {
+    pub description: String,
+    #[serde(default)]
+    pub done: bool,
+    pub birth: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub priority: u8,
+    pub due: Option<NaiveDate>,
+    pub done_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    pub recur: Option<Recurrence>,
+    #[serde(default)]
+    pub archived: bool,
+}
 
+impl Task {
     /// Add a new task to the database.
     ///
     /// Creates a new task with the given description and the current timestamp.
@@ -64,7 +310,11 @@ impl Task {
     /// # Arguments
     ///
     /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
     /// * `description` - The task description
+    /// * `priority` - Optional priority level, defaults to 0 (none) when absent
+    /// * `due` - Optional due date
+    /// * `utc` - Store the creation time in UTC instead of local time
     ///
     /// # Returns
     ///
@@ -78,17 +328,64 @@ impl Task {
     ///
     /// ```
     /// let conn = Connection::open("tasks.db")?;
-    /// let task_id = Task::add(&conn, "Buy groceries".to_string())?;
+    /// let due = NaiveDate::from_ymd_opt(2024, 12, 24);
+    /// let task_id = Task::add(&conn, "default", "Buy groceries".to_string(), Some(2), due, false)?;
     /// println!("Created task with ID: {}", task_id);
     /// ```
-    pub fn add(conn: &Connection, description: String) -> Result<i64> {
-        let now = Local::now().naive_local();
-        let birth_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    pub fn add(
+        conn: &Connection,
+        profile: &str,
+        description: String,
+        priority: Option<u8>,
+        due: Option<NaiveDate>,
+        utc: bool,
+    ) -> Result<i64> {
+        let birth_str = now_timestamp(utc);
+        let priority = priority.unwrap_or(0);
+        let due_str = due.map(|d| d.format(DATE_FORMAT).to_string());
 
-        conn.execute(crate::database::INSERT_TASK, (&description, &birth_str))?;
+        conn.execute(
+            crate::database::INSERT_TASK,
+            (&description, &birth_str, &priority, &due_str, profile),
+        )?;
         Ok(conn.last_insert_rowid())
     }
 
+    /// Attach tags to a task.
+    ///
+    /// Each tag is normalized with `normalize_tag`. Tags the task already
+    /// has are left alone, so calling this twice with the same tags is safe.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `id` - The ID of the task to tag
+    /// * `tags` - The tags to attach
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` once every tag has been stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a tag is invalid or the database insertion fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let id = Task::add(&conn, "default", "Call plumber".to_string(), None, None, false)?;
+    /// Task::add_tags(&conn, id, &["home".to_string()])?;
+    /// ```
+    pub fn add_tags(conn: &Connection, id: i64, tags: &[String]) -> Result<()> {
+        for tag in tags {
+            let tag = normalize_tag(tag)?;
+            conn.execute(crate::database::INSERT_TASK_TAG, (id, &tag))?;
+        }
+
+        Ok(())
+    }
+
     /// Retrieve all tasks from the database.
     ///
     /// Fetches all tasks regardless of their completion status and returns
@@ -97,6 +394,7 @@ impl Task {
     /// # Arguments
     ///
     /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
     ///
     /// # Returns
     ///
@@ -106,106 +404,2300 @@ impl Task {
     ///
     /// Returns an error if:
     /// - The database query fails
-    /// - Task data is corrupted (e.g., invalid timestamp format)
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
     ///
     /// # Examples
     ///
     /// ```
     /// let conn = Connection::open("tasks.db")?;
-    /// let tasks = Task::list(&conn)?;
+    /// let tasks = Task::list(&conn, "default")?;
     /// for task in tasks {
     ///     println!("{}: {}", task.id, task.description);
     /// }
     /// ```
-    pub fn list(conn: &Connection) -> Result<Vec<Task>> {
-        let mut stmt = conn.prepare(crate::database::SELECT_ALL_TASKS)?;
-        let task_iter = stmt.query_map([], |row| {
-            let date_str: String = row.get(3)?;
-            let parsed =
-                NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S").map_err(|_e| {
-                    rusqlite::Error::InvalidColumnType(
-                        3,
-                        date_str.clone(),
-                        rusqlite::types::Type::Text,
-                    )
-                })?;
-
-            Ok(Task {
-                id: row.get(0)?,
-                description: row.get(1)?,
-                done: row.get(2)?,
-                birth: parsed,
-            })
-        })?;
+    pub fn list(conn: &Connection, profile: &str) -> Result<Vec<Task>> {
+        let sql = format!("{} WHERE list_name = ?1", crate::database::SELECT_ALL_TASKS);
+        Task::query_tasks(conn, &sql, [profile])
+    }
+
+    /// Retrieve tasks matching the given listing options.
+    ///
+    /// Builds the query from `SELECT_ALL_TASKS` plus a `WHERE` clause for
+    /// any filters and an `ORDER BY` clause for the requested sort. Sort
+    /// columns come from the `SortKey` whitelist and filter values are bound
+    /// as parameters, so no user input is ever interpolated into the SQL.
+    /// Ties are broken by ascending ID to keep the output stable, which also
+    /// makes paging with `limit` and `offset` deterministic.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `options` - Filters and ordering to apply
+    ///
+    /// # Returns
+    ///
+    /// Returns a vector of the matching tasks in the requested order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The database query fails
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let options = ListOptions {
+    ///     done: Some(false),
+    ///     sort: SortKey::Birth,
+    ///     reverse: true,
+    ///     tag: None,
+    ///     since: None,
+    ///     until: None,
+    ///     created_before: None,
+    ///     limit: Some(10),
+    ///     offset: None,
+    ///     archived: false,
+    /// };
+    /// let newest_pending = Task::list_with(&conn, "default", &options)?;
+    /// ```
+    pub fn list_with(conn: &Connection, profile: &str, options: &ListOptions) -> Result<Vec<Task>> {
+        let mut conditions: Vec<&str> = vec!["list_name = ?", "archived = ?"];
+        let mut params: Vec<Value> = vec![
+            Value::from(profile.to_string()),
+            Value::from(options.archived),
+        ];
+
+        if let Some(done) = options.done {
+            conditions.push("done = ?");
+            params.push(Value::from(done));
+        }
+
+        if let Some(tag) = &options.tag {
+            conditions.push("id IN (SELECT task_id FROM task_tags WHERE tag = ?)");
+            params.push(Value::from(tag.clone()));
+        }
 
-        Ok(task_iter.filter_map(Result::ok).collect())
+        // Creation days are compared in local time, whichever way they were stored
+        if let Some(since) = options.since {
+            conditions.push(
+                "CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END >= ?",
+            );
+            params.push(Value::from(
+                since
+                    .and_time(NaiveTime::MIN)
+                    .format(TIMESTAMP_FORMAT)
+                    .to_string(),
+            ));
+        }
+
+        if let Some(until) = options.until {
+            // Everything before the start of the next day, so `until` is inclusive
+            let end = until.succ_opt().unwrap_or(NaiveDate::MAX);
+            conditions.push(
+                "CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END < ?",
+            );
+            params.push(Value::from(
+                end.and_time(NaiveTime::MIN)
+                    .format(TIMESTAMP_FORMAT)
+                    .to_string(),
+            ));
+        }
+
+        if let Some(before) = options.created_before {
+            conditions.push(
+                "CASE WHEN birth LIKE '%Z' THEN datetime(birth, 'localtime') ELSE birth END < ?",
+            );
+            params.push(Value::from(before.format(TIMESTAMP_FORMAT).to_string()));
+        }
+
+        let mut sql = String::from(crate::database::SELECT_ALL_TASKS);
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+
+        let direction = if options.reverse { "DESC" } else { "ASC" };
+        sql.push_str(&format!(
+            " ORDER BY {} {}, id ASC",
+            options.sort.column(),
+            direction
+        ));
+
+        if options.limit.is_some() || options.offset.is_some() {
+            // SQLite needs a LIMIT for OFFSET; a negative one means no limit
+            sql.push_str(" LIMIT ? OFFSET ?");
+            params.push(Value::from(options.limit.map_or(-1, i64::from)));
+            params.push(Value::from(options.offset.unwrap_or(0)));
+        }
+
+        Task::query_tasks(conn, &sql, params_from_iter(params))
     }
 
-    /// Remove a task from the database.
+    /// Retrieve tasks matching the given listing options, with subtasks
+    /// nested under their parent.
     ///
-    /// Deletes the task with the specified ID from the database.
+    /// The matching tasks are fetched with `Task::list_with` and arranged in
+    /// memory: every subtask follows its parent, with its `depth` set one
+    /// deeper, and siblings keep the requested order. A subtask whose parent
+    /// isn't among the matching tasks, for example because it is filtered
+    /// out, is listed as a top-level task.
     ///
     /// # Arguments
     ///
     /// * `conn` - SQLite database connection
-    /// * `id` - The ID of the task to remove
+    /// * `profile` - The profile (task list) to work in
+    /// * `options` - Filters and ordering to apply
     ///
     /// # Returns
     ///
-    /// Returns `true` if a task was deleted, `false` if no task with the given ID exists.
+    /// Returns the matching tasks in tree order.
     ///
     /// # Errors
     ///
-    /// Returns an error if the database operation fails.
+    /// Returns an error if:
+    /// - The database query fails
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
     ///
     /// # Examples
     ///
     /// ```
     /// let conn = Connection::open("tasks.db")?;
-    /// let removed = Task::remove(&conn, 1)?;
-    /// if removed {
-    ///     println!("Task removed successfully");
-    /// } else {
-    ///     println!("No task found with that ID");
+    /// for task in Task::list_tree(&conn, "default", &ListOptions::default())? {
+    ///     println!("{}{}", "  ".repeat(task.depth), task.description);
     /// }
     /// ```
-    pub fn remove(conn: &Connection, id: i64) -> Result<bool> {
-        let rows_affected = conn.execute(crate::database::DELETE_TASK, [&id])?;
-        Ok(rows_affected > 0)
+    pub fn list_tree(conn: &Connection, profile: &str, options: &ListOptions) -> Result<Vec<Task>> {
+        let tasks = Task::list_with(conn, profile, options)?;
+        let listed: HashSet<i64> = tasks.iter().map(|task| task.id).collect();
+
+        let mut roots = Vec::new();
+        let mut children: HashMap<i64, Vec<Task>> = HashMap::new();
+        for task in tasks {
+            match task.parent_id.filter(|parent| listed.contains(parent)) {
+                Some(parent) => children.entry(parent).or_default().push(task),
+                None => roots.push(task),
+            }
+        }
+
+        // Depth-first, so the stack holds siblings in reverse order
+        let mut ordered = Vec::with_capacity(listed.len());
+        let mut stack: Vec<Task> = roots.into_iter().rev().collect();
+        while let Some(task) = stack.pop() {
+            if let Some(subtasks) = children.remove(&task.id) {
+                for mut subtask in subtasks.into_iter().rev() {
+                    subtask.depth = task.depth + 1;
+                    stack.push(subtask);
+                }
+            }
+            ordered.push(task);
+        }
+
+        Ok(ordered)
     }
 
-    /// Mark a task as completed.
+    /// Retrieve a single task by ID.
     ///
-    /// Updates the task's status to completed (done = true) if it exists
-    /// and is not already completed.
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to fetch
+    ///
+    /// # Returns
+    ///
+    /// Returns the task, or `None` if no task with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The database query fails
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// if let Some(task) = Task::get(&conn, "default", 1)? {
+    ///     println!("{}", task.description);
+    /// }
+    /// ```
+    pub fn get(conn: &Connection, profile: &str, id: i64) -> Result<Option<Task>> {
+        Ok(Task::query_tasks(conn, crate::database::SELECT_TASK, (id, profile))?.pop())
+    }
+
+    /// Find a pending task with exactly the given description.
+    ///
+    /// Unlike `search`, the description must match in full, including case.
+    /// Completed and archived tasks are ignored. If several tasks match, the
+    /// one with the lowest ID is returned.
     ///
     /// # Arguments
     ///
     /// * `conn` - SQLite database connection
-    /// * `id` - The ID of the task to mark as done
+    /// * `profile` - The profile (task list) to work in
+    /// * `description` - The description to look for
     ///
     /// # Returns
     ///
-    /// Returns `true` if the task was updated, `false` if no incomplete task
-    /// with the given ID exists.
+    /// Returns the matching task, or `None` if there isn't one.
     ///
     /// # Errors
     ///
-    /// Returns an error if the database operation fails.
+    /// Returns an error if:
+    /// - The database query fails
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
     ///
     /// # Examples
     ///
     /// ```
     /// let conn = Connection::open("tasks.db")?;
-    /// let updated = Task::mark_done(&conn, 1)?;
-    /// if updated {
-    ///     println!("Task marked as done");
-    /// } else {
-    ///     println!("Task already completed or doesn't exist");
+    /// if let Some(task) = Task::find_by_description(&conn, "default", "Pay rent")? {
+    ///     println!("Already on the list as task {}", task.id);
     /// }
     /// ```
-    pub fn mark_done(conn: &Connection, id: i64) -> Result<bool> {
-        let rows_affected = conn.execute(crate::database::UPDATE_TASK_DONE, [&id])?;
-        Ok(rows_affected > 0)
+    pub fn find_by_description(
+        conn: &Connection,
+        profile: &str,
+        description: &str,
+    ) -> Result<Option<Task>> {
+        let tasks = Task::query_tasks(
+            conn,
+            crate::database::FIND_PENDING_BY_DESCRIPTION,
+            (description, profile),
+        )?;
+        Ok(tasks.into_iter().next())
+    }
+
+    /// Search tasks by description.
+    ///
+    /// Returns every task whose description contains `term`. Matching is
+    /// case-insensitive for ASCII characters, following SQLite's `LIKE`.
+    /// The `%` and `_` wildcards are escaped, so they match literally.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `term` - The substring to look for
+    ///
+    /// # Returns
+    ///
+    /// Returns a vector of matching tasks, which may be empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The database query fails
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let tasks = Task::search(&conn, "default", "invoice")?;
+    /// println!("Found {} matching tasks", tasks.len());
+    /// ```
+    pub fn search(conn: &Connection, profile: &str, term: &str) -> Result<Vec<Task>> {
+        let escaped = term
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+
+        Task::query_tasks(conn, crate::database::SEARCH_TASKS, [&pattern, profile])
+    }
+
+    /// Search tasks by description, tolerating missing characters.
+    ///
+    /// Every task of the profile is scored against `term` with the skim
+    /// fuzzy matching algorithm, ignoring case: the characters of `term` must
+    /// appear in the description in order, but not necessarily next to each
+    /// other, and matches on word starts or consecutive characters score
+    /// higher. Matches scoring below `FUZZY_MIN_SCORE_PER_CHAR` per character
+    /// of `term` are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `term` - The text to look for
+    ///
+    /// # Returns
+    ///
+    /// Returns the matching tasks, best match first. Equal scores are
+    /// ordered by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails or task data is corrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// for found in Task::search_fuzzy(&conn, "default", "invc")? {
+    ///     println!("{} {}", found.score, found.task.description);
+    /// }
+    /// ```
+    pub fn search_fuzzy(conn: &Connection, profile: &str, term: &str) -> Result<Vec<SearchMatch>> {
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let chars = i64::try_from(term.chars().count()).unwrap_or(i64::MAX);
+        let min_score = FUZZY_MIN_SCORE_PER_CHAR.saturating_mul(chars);
+
+        let mut matches: Vec<SearchMatch> = Task::list(conn, profile)?
+            .into_iter()
+            .filter_map(|task| {
+                let score = matcher.fuzzy_match(&task.description, term)?;
+                (score >= min_score).then_some(SearchMatch { score, task })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.task.id.cmp(&b.task.id)));
+
+        Ok(matches)
+    }
+
+    /// Retrieve tasks created or due on a given day.
+    ///
+    /// A task is included if its creation time falls within the day in
+    /// local time, or if its due date is that day.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `date` - The day to list tasks for
+    ///
+    /// # Returns
+    ///
+    /// Returns the matching tasks ordered by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The database query fails
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let today = Local::now().naive_local().date();
+    /// let tasks = Task::list_for_day(&conn, "default", today)?;
+    /// ```
+    pub fn list_for_day(conn: &Connection, profile: &str, date: NaiveDate) -> Result<Vec<Task>> {
+        let start = date.and_time(NaiveTime::MIN);
+        let end = date
+            .succ_opt()
+            .unwrap_or(NaiveDate::MAX)
+            .and_time(NaiveTime::MIN);
+
+        Task::query_tasks(
+            conn,
+            crate::database::SELECT_TASKS_FOR_DAY,
+            [
+                start.format(TIMESTAMP_FORMAT).to_string(),
+                end.format(TIMESTAMP_FORMAT).to_string(),
+                date.format(DATE_FORMAT).to_string(),
+                profile.to_string(),
+            ],
+        )
+    }
+
+    /// Retrieve tasks completed within a time range.
+    ///
+    /// Only tasks with a recorded completion time are returned, so tasks
+    /// completed before `done_at` was tracked never appear.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `start` - The earliest completion time to include
+    /// * `end` - The latest completion time to include
+    ///
+    /// # Returns
+    ///
+    /// Returns the matching tasks ordered by completion time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The database query fails
+    /// - Task data is corrupted (e.g., invalid timestamp or due date format)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let end = Local::now().naive_local();
+    /// let start = end - chrono::Duration::days(7);
+    /// let recent = Task::completed_between(&conn, "default", start, end)?;
+    /// ```
+    pub fn completed_between(
+        conn: &Connection,
+        profile: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<Task>> {
+        // The query compares local times, whichever way they were stored
+        let start_str = start.format(TIMESTAMP_FORMAT).to_string();
+        let end_str = end.format(TIMESTAMP_FORMAT).to_string();
+
+        Task::query_tasks(
+            conn,
+            crate::database::SELECT_COMPLETED_BETWEEN,
+            [&start_str, &end_str, profile],
+        )
+    }
+
+    /// List the profiles that have at least one task.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    ///
+    /// # Returns
+    ///
+    /// Returns the profile names in alphabetical order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// for profile in Task::profiles(&conn)? {
+    ///     println!("{}", profile);
+    /// }
+    /// ```
+    pub fn profiles(conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(crate::database::SELECT_PROFILES)?;
+        let profiles = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(profiles)
+    }
+
+    /// Run a task query and collect every resulting row.
+    ///
+    /// The query must select the standard task columns in the order used by
+    /// `SELECT_ALL_TASKS`. Any row that fails to parse aborts the query with
+    /// an error instead of being silently skipped.
+    fn query_tasks<P: Params>(conn: &Connection, sql: &str, params: P) -> Result<Vec<Task>> {
+        let mut stmt = conn.prepare(sql)?;
+        let mut rows = stmt.query(params)?;
+
+        let mut tasks = Vec::new();
+        while let Some(row) = rows.next()? {
+            tasks.push(Task::from_row(row)?);
+        }
+
+        Ok(tasks)
+    }
+
+    /// Build a `Task` from a row selected with the standard task columns.
+    ///
+    /// Expects the columns in the order used by `SELECT_ALL_TASKS`:
+    /// `id, description, done, birth, priority, due, done_at, tags, notes, recur,
+    /// parent_id, archived`.
+    ///
+    /// A stored value that can't be parsed produces an error naming the task
+    /// and column, so corrupt rows are reported instead of silently skipped.
+    fn from_row(row: &rusqlite::Row) -> Result<Task> {
+        let id: i64 = row.get(0)?;
+
+        let date_str: String = row.get(3)?;
+        let parsed = parse_stored_timestamp(&date_str).with_context(|| {
+            format!("Task {} has an invalid birth timestamp '{}'", id, date_str)
+        })?;
+
+        let due_str: Option<String> = row.get(5)?;
+        let due = due_str
+            .map(|s| {
+                NaiveDate::parse_from_str(&s, DATE_FORMAT)
+                    .with_context(|| format!("Task {} has an invalid due date '{}'", id, s))
+            })
+            .transpose()?;
+
+        let done_at_str: Option<String> = row.get(6)?;
+        let done_at = done_at_str
+            .map(|s| {
+                parse_stored_timestamp(&s).with_context(|| {
+                    format!("Task {} has an invalid done_at timestamp '{}'", id, s)
+                })
+            })
+            .transpose()?;
+
+        let tags_str: Option<String> = row.get(7)?;
+        let mut tags: Vec<String> = tags_str
+            .map(|s| s.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        tags.sort();
+
+        let recur_str: Option<String> = row.get(9)?;
+        let recur = recur_str
+            .map(|s| {
+                s.parse::<Recurrence>()
+                    .with_context(|| format!("Task {} has an invalid recurrence '{}'", id, s))
+            })
+            .transpose()?;
+
+        Ok(Task {
+            id,
+            description: row.get(1)?,
+            done: row.get(2)?,
+            birth: parsed,
+            priority: row.get(4)?,
+            due,
+            done_at,
+            tags,
+            notes: row.get(8)?,
+            recur,
+            parent_id: row.get(10)?,
+            archived: row.get(11)?,
+            depth: 0,
+        })
+    }
+
+    /// Count pending and completed tasks.
+    ///
+    /// Both counts come from a single aggregate query, and an empty table
+    /// yields `(0, 0)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    ///
+    /// # Returns
+    ///
+    /// Returns a `(pending, done)` tuple.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let (pending, done) = Task::counts(&conn, "default")?;
+    /// println!("{} pending, {} done", pending, done);
+    /// ```
+    pub fn counts(conn: &Connection, profile: &str) -> Result<(usize, usize)> {
+        let (total, done): (usize, usize) =
+            conn.query_row(crate::database::COUNT_TASKS, [profile], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?;
+
+        Ok((total - done, done))
+    }
+
+    /// Count tasks, optionally only those with a given completion status.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `done` - Only count tasks with this completion status, if set
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of matching tasks, 0 for an empty database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let pending = Task::count(&conn, "default", Some(false))?;
+    /// ```
+    pub fn count(conn: &Connection, profile: &str, done: Option<bool>) -> Result<usize> {
+        let count = conn.query_row(
+            crate::database::COUNT_TASKS_WITH_STATUS,
+            (done, profile),
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Compute summary statistics for all tasks.
+    ///
+    /// Counts are computed with SQL aggregates, so the tasks themselves are
+    /// never loaded into memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    ///
+    /// # Returns
+    ///
+    /// Returns a `TaskStats` with the total, done and pending counts, the
+    /// completion percentage, the age of the oldest pending task and the
+    /// number of tasks created in the last 7 days.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails or the oldest pending
+    /// task has an invalid creation timestamp.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let stats = Task::stats(&conn, "default")?;
+    /// println!("{:.0}% complete", stats.completion_percentage);
+    /// ```
+    pub fn stats(conn: &Connection, profile: &str) -> Result<TaskStats> {
+        let (pending, done) = Self::counts(conn, profile)?;
+        let total = pending + done;
+
+        let completion_percentage = if total == 0 {
+            0.0
+        } else {
+            done as f64 * 100.0 / total as f64
+        };
+
+        let now = Local::now().naive_local();
+        let week_ago = (now - chrono::Duration::days(7))
+            .format(TIMESTAMP_FORMAT)
+            .to_string();
+        let (oldest_pending, created_last_7_days): (Option<String>, usize) = conn.query_row(
+            crate::database::TASK_AGE_STATS,
+            [&week_ago, profile],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let oldest_pending_days = oldest_pending
+            .map(|birth| {
+                NaiveDateTime::parse_from_str(&birth, TIMESTAMP_FORMAT)
+                    .with_context(|| format!("Invalid birth timestamp '{}'", birth))
+            })
+            .transpose()?
+            .map(|birth| (now - birth).num_days());
+
+        Ok(TaskStats {
+            total,
+            done,
+            pending,
+            completion_percentage,
+            oldest_pending_days,
+            created_last_7_days,
+        })
+    }
+
+    /// Remove a task from the database.
+    ///
+    /// Deletes the task with the specified ID from the database. A copy of
+    /// the task and its tags is kept in the undo log first, so the removal
+    /// can be reverted with `Task::undo_last`. Run it in a transaction so
+    /// the copy and the deletion apply together.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to remove
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if a task was deleted, `false` if no task with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let removed = Task::remove(&conn, "default", 1)?;
+    /// if removed {
+    ///     println!("Task removed successfully");
+    /// } else {
+    ///     println!("No task found with that ID");
+    /// }
+    /// ```
+    pub fn remove(conn: &Connection, profile: &str, id: i64) -> Result<bool> {
+        if conn.execute(crate::database::SNAPSHOT_TASK, (id, profile))? == 0 {
+            return Ok(false);
+        }
+        conn.execute(
+            crate::database::TRIM_UNDO_LOG,
+            [crate::database::UNDO_LOG_LIMIT],
+        )?;
+
+        let rows_affected = conn.execute(crate::database::DELETE_TASK, (id, profile))?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Restore the most recently removed task.
+    ///
+    /// Recreates the profile's newest task in the undo log with its description,
+    /// status, timestamps, priority, due date and tags, then drops that
+    /// entry so repeated calls restore older removals in turn. The restored
+    /// task gets a new ID. Run it in a transaction so the restore and the
+    /// log update apply together.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    ///
+    /// # Returns
+    ///
+    /// Returns the restored task, or `None` if the profile has nothing to undo.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails or the restored
+    /// task's data is corrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let restored = with_transaction(&mut conn, |tx| Task::undo_last(tx, "default"))?;
+    /// if let Some(task) = restored {
+    ///     println!("Restored '{}' as task {}", task.description, task.id);
+    /// }
+    /// ```
+    pub fn undo_last(conn: &Connection, profile: &str) -> Result<Option<Task>> {
+        let entry = conn
+            .query_row(crate::database::SELECT_LAST_UNDO, [profile], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+            })
+            .optional()?;
+
+        let Some((entry_id, tags)) = entry else {
+            return Ok(None);
+        };
+
+        conn.execute(crate::database::RESTORE_TASK, [entry_id])?;
+        let id = conn.last_insert_rowid();
+
+        let tags: Vec<String> = tags
+            .map(|s| s.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        Task::add_tags(conn, id, &tags)?;
+
+        conn.execute(crate::database::DELETE_UNDO_ENTRY, [entry_id])?;
+        Task::get(conn, profile, id)
+    }
+
+    /// Remove all completed tasks of a profile from the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of tasks that were removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let removed = Task::clear_completed(&conn, "default")?;
+    /// println!("Removed {} completed tasks", removed);
+    /// ```
+    pub fn clear_completed(conn: &Connection, profile: &str) -> Result<usize> {
+        let rows_affected = conn.execute(crate::database::DELETE_DONE_TASKS, [profile])?;
+        Ok(rows_affected)
+    }
+
+    /// Mark a task as completed.
+    ///
+    /// Updates the task's status to completed (done = true) if it exists
+    /// and is not already completed, recording the current time as its
+    /// completion timestamp.
+    ///
+    /// Completing a recurring task also adds its next occurrence: a pending
+    /// copy with the same description, priority, tags, notes, interval and
+    /// parent, created now and due one interval after the completed task's
+    /// due date, if it had one. The completed task stays in the history. A
+    /// task gets only one next occurrence, so completing it again after
+    /// `mark_undone` adds none while the first one still exists. Run it
+    /// inside a transaction so a task is never completed without its next
+    /// occurrence.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to mark as done
+    /// * `utc` - Store the completion time in UTC instead of local time
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task was updated, `false` if no incomplete task
+    /// with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let updated = Task::mark_done(&conn, "default", 1, false)?;
+    /// if updated {
+    ///     println!("Task marked as done");
+    /// } else {
+    ///     println!("Task already completed or doesn't exist");
+    /// }
+    /// ```
+    pub fn mark_done(conn: &Connection, profile: &str, id: i64, utc: bool) -> Result<bool> {
+        let done_at = now_timestamp(utc);
+        let rows_affected =
+            conn.execute(crate::database::UPDATE_TASK_DONE, (&id, &done_at, profile))?;
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+
+        if let Some(task) = Task::get(conn, profile, id)? {
+            if let Some(recur) = task.recur {
+                let has_next: bool =
+                    conn.query_row(crate::database::HAS_NEXT_OCCURRENCE, [id], |row| row.get(0))?;
+                if !has_next {
+                    Task::add_next_occurrence(conn, profile, &task, recur, utc)?;
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Add the next occurrence of a recurring task.
+    ///
+    /// The new task records `task` as the task it recurs from.
+    ///
+    /// Returns the ID of the new task.
+    fn add_next_occurrence(
+        conn: &Connection,
+        profile: &str,
+        task: &Task,
+        recur: Recurrence,
+        utc: bool,
+    ) -> Result<i64> {
+        let due = task
+            .due
+            .map(|due| {
+                recur.next_date(due).with_context(|| {
+                    format!("Task {} can't repeat {} after {}", task.id, recur, due)
+                })
+            })
+            .transpose()?;
+
+        let id = Task::add_copy(conn, profile, task, due, utc)?;
+        conn.execute(crate::database::UPDATE_TASK_RECURRED_FROM, (id, task.id))?;
+        Ok(id)
+    }
+
+    /// Add a pending copy of a task.
+    ///
+    /// The copy gets a fresh ID and creation time, the given due date and
+    /// the description, priority, tags, notes, recurrence and parent of
+    /// `task`.
+    ///
+    /// Returns the ID of the new task.
+    fn add_copy(
+        conn: &Connection,
+        profile: &str,
+        task: &Task,
+        due: Option<NaiveDate>,
+        utc: bool,
+    ) -> Result<i64> {
+        let id = Task::add(
+            conn,
+            profile,
+            task.description.clone(),
+            Some(task.priority),
+            due,
+            utc,
+        )?;
+        Task::add_tags(conn, id, &task.tags)?;
+        if let Some(notes) = &task.notes {
+            Task::set_note(conn, profile, id, notes)?;
+        }
+        if let Some(recur) = task.recur {
+            Task::set_recurrence(conn, id, recur)?;
+        }
+        if let Some(parent_id) = task.parent_id {
+            Task::set_parent(conn, id, parent_id)?;
+        }
+
+        Ok(id)
+    }
+
+    /// Duplicate a task.
+    ///
+    /// Adds a pending, unarchived copy of the task with a fresh ID and
+    /// creation time. The copy keeps the description, priority, due date,
+    /// tags, notes and recurrence of the original, and is a subtask of the
+    /// same parent.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to copy
+    /// * `utc` - Store the creation time in UTC instead of local time
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID of the copy, or `None` if no task with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// if let Some(copy) = Task::duplicate(&conn, "default", 3, false)? {
+    ///     println!("Task 3 duplicated as task {}", copy);
+    /// }
+    /// ```
+    pub fn duplicate(conn: &Connection, profile: &str, id: i64, utc: bool) -> Result<Option<i64>> {
+        let Some(task) = Task::get(conn, profile, id)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Task::add_copy(conn, profile, &task, task.due, utc)?))
+    }
+
+    /// Mark a completed task as not done.
+    ///
+    /// Reverts the task's status to pending (done = false) if it exists
+    /// and is currently completed, clearing its completion timestamp.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to mark as not done
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task was updated, `false` if no completed task
+    /// with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let updated = Task::mark_undone(&conn, "default", 1)?;
+    /// if updated {
+    ///     println!("Task marked as not done");
+    /// } else {
+    ///     println!("Task not completed or doesn't exist");
+    /// }
+    /// ```
+    pub fn mark_undone(conn: &Connection, profile: &str, id: i64) -> Result<bool> {
+        let rows_affected = conn.execute(crate::database::UPDATE_TASK_UNDONE, (id, profile))?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Archive a task.
+    ///
+    /// Archived tasks stay in the database, with their completion history,
+    /// but are hidden from `list` unless `--archived` is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to archive
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task was archived, `false` if no task with the
+    /// given ID exists or it is already archived.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// if Task::archive(&conn, "default", 1)? {
+    ///     println!("Task archived");
+    /// }
+    /// ```
+    pub fn archive(conn: &Connection, profile: &str, id: i64) -> Result<bool> {
+        let rows_affected = conn.execute(crate::database::ARCHIVE_TASK, (id, profile))?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Archive every task of a profile created before a given day.
+    ///
+    /// Tasks created on `before` itself, in local time, are kept. Run it
+    /// inside a transaction along with any other changes it belongs to.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `before` - The first day whose tasks are kept
+    /// * `done_only` - Only archive completed tasks, leaving pending ones listed
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of tasks archived; ones already archived aren't counted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let before = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    /// let archived = Task::archive_before(&conn, "default", before, true)?;
+    /// println!("Archived {} completed tasks", archived);
+    /// ```
+    pub fn archive_before(
+        conn: &Connection,
+        profile: &str,
+        before: NaiveDate,
+        done_only: bool,
+    ) -> Result<usize> {
+        let before = before
+            .and_time(NaiveTime::MIN)
+            .format(TIMESTAMP_FORMAT)
+            .to_string();
+        let rows_affected = conn.execute(
+            crate::database::ARCHIVE_TASKS_BEFORE,
+            (profile, &before, done_only),
+        )?;
+        Ok(rows_affected)
+    }
+
+    /// Get a human-readable label for the task's priority.
+    ///
+    /// # Returns
+    ///
+    /// Returns one of `"none"`, `"low"`, `"medium"` or `"high"`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// println!("Priority: {}", task.priority_label());
+    /// ```
+    pub fn priority_label(&self) -> &'static str {
+        match self.priority {
+            0 => "none",
+            1 => "low",
+            2 => "medium",
+            _ => "high",
+        }
+    }
+
+    /// Check whether the task is past its due date.
+    ///
+    /// A task is overdue when it has a due date earlier than `today` and
+    /// has not been completed yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `today` - The current local date
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task is pending and its due date has passed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let today = Local::now().date_naive();
+    /// if task.is_overdue(today) {
+    ///     println!("Task {} is overdue!", task.id);
+    /// }
+    /// ```
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.done && self.due.is_some_and(|due| due < today)
+    }
+
+    /// Set or remove the notes of an existing task.
+    ///
+    /// Only the notes are changed. An empty or whitespace-only note removes
+    /// the task's notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to edit
+    /// * `note` - The new notes
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task was updated, `false` if no task with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// Task::set_note(&conn, "default", 1, "Ask for the blue one\nReceipt in the drawer")?;
+    /// ```
+    pub fn set_note(conn: &Connection, profile: &str, id: i64, note: &str) -> Result<bool> {
+        let note = Some(note).filter(|n| !n.trim().is_empty());
+        let rows_affected =
+            conn.execute(crate::database::UPDATE_TASK_NOTES, (&id, &note, profile))?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Move a task to a new position in the default listing order.
+    ///
+    /// Positions count from 1 at the top of `list`; a position past the end
+    /// moves the task to the bottom. Every task in the profile is renumbered from 1 in its
+    /// new order, so repeated moves never leave gaps or duplicate positions.
+    /// Run it inside a transaction so a failure can't leave the list
+    /// half-renumbered.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to move
+    /// * `position` - The new position, starting at 1
+    ///
+    /// # Returns
+    ///
+    /// Returns the position the task ended up at, or `None` if no task with
+    /// the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let moved = with_transaction(&mut conn, |tx| Task::move_to(tx, "default", 7, 1))?;
+    /// ```
+    pub fn move_to(
+        conn: &Connection,
+        profile: &str,
+        id: i64,
+        position: i64,
+    ) -> Result<Option<i64>> {
+        let mut stmt = conn.prepare(crate::database::SELECT_TASK_IDS_BY_POSITION)?;
+        let mut ids = stmt
+            .query_map([profile], |row| row.get::<_, i64>(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+        let Some(current) = ids.iter().position(|&task_id| task_id == id) else {
+            return Ok(None);
+        };
+        ids.remove(current);
+        let index = usize::try_from(position.saturating_sub(1))
+            .unwrap_or(0)
+            .min(ids.len());
+        ids.insert(index, id);
+
+        let mut update = conn.prepare(crate::database::UPDATE_TASK_POSITION)?;
+        for (index, task_id) in ids.iter().enumerate() {
+            update.execute((task_id, index as i64 + 1))?;
+        }
+
+        Ok(Some(index as i64 + 1))
+    }
+
+    /// Renumber all tasks so their IDs run from 1 without gaps.
+    ///
+    /// Tasks keep their order, every field and their tags, and subtasks stay
+    /// under their parent. IDs are shared by all profiles, so every profile
+    /// is renumbered together. New tasks continue after the highest ID. Run
+    /// it inside a transaction: foreign keys are only checked when it
+    /// commits, and a failure must not leave IDs half rewritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` once every ID has been rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// with_transaction(&mut conn, |tx| Task::renumber(tx))?;
+    /// ```
+    pub fn renumber(conn: &Connection) -> Result<()> {
+        conn.execute_batch(crate::database::RENUMBER_TASKS)?;
+        Ok(())
+    }
+
+    /// Make an existing task a subtask of another task.
+    ///
+    /// The caller checks that the parent exists in the same profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `id` - The ID of the subtask
+    /// * `parent_id` - The ID of the parent task
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task was updated, `false` if no task with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails, for example because
+    /// the parent task doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// Task::set_parent(&conn, 8, 3)?;
+    /// ```
+    pub fn set_parent(conn: &Connection, id: i64, parent_id: i64) -> Result<bool> {
+        let rows_affected = conn.execute(crate::database::UPDATE_TASK_PARENT, (id, parent_id))?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Make an existing task recur.
+    ///
+    /// Once the task is completed, `mark_done` adds its next occurrence.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `id` - The ID of the task to repeat
+    /// * `recur` - How often the task comes back
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task was updated, `false` if no task with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// Task::set_recurrence(&conn, 1, "weekly".parse()?)?;
+    /// ```
+    pub fn set_recurrence(conn: &Connection, id: i64, recur: Recurrence) -> Result<bool> {
+        let rows_affected = conn.execute(
+            crate::database::UPDATE_TASK_RECUR,
+            (&id, &recur.to_string()),
+        )?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Update the description of an existing task.
+    ///
+    /// Only the description is changed; the task keeps its ID, completion
+    /// status and creation timestamp.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `id` - The ID of the task to edit
+    /// * `description` - The new task description
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the task was updated, `false` if no task with the given ID exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let updated = Task::edit(&conn, "default", 1, "Buy groceries and milk".to_string())?;
+    /// if updated {
+    ///     println!("Task updated");
+    /// } else {
+    ///     println!("No task found with that ID");
+    /// }
+    /// ```
+    pub fn edit(conn: &Connection, profile: &str, id: i64, description: String) -> Result<bool> {
+        let rows_affected = conn.execute(
+            crate::database::UPDATE_TASK_DESCRIPTION,
+            (&id, &description, profile),
+        )?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Export all tasks of a profile as CSV.
+    ///
+    /// Writes a header row
+    /// (`id,description,done,birth,priority,due,done_at,tags,notes,recur,archived`)
+    /// followed by one row per task, with each task's tags joined by commas.
+    /// Fields containing commas, quotes or line breaks are quoted so the
+    /// output can be opened in any spreadsheet.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `writer` - Destination for the CSV data
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of tasks written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tasks cannot be read or the writer fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let file = File::create("tasks.csv")?;
+    /// let count = Task::export_csv(&conn, "default", file)?;
+    /// println!("Exported {} tasks", count);
+    /// ```
+    pub fn export_csv<W: Write>(conn: &Connection, profile: &str, mut writer: W) -> Result<usize> {
+        let tasks = Task::list(conn, profile)?;
+
+        writeln!(
+            writer,
+            "id,description,done,birth,priority,due,done_at,tags,notes,recur,archived"
+        )?;
+        for task in &tasks {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                task.id,
+                crate::io_utils::csv_field(&task.description),
+                task.done,
+                task.birth.format(TIMESTAMP_FORMAT),
+                task.priority,
+                task.due.map(|d| d.to_string()).unwrap_or_default(),
+                task.done_at
+                    .map(|t| t.format(TIMESTAMP_FORMAT).to_string())
+                    .unwrap_or_default(),
+                crate::io_utils::csv_field(&task.tags.join(",")),
+                crate::io_utils::csv_field(task.notes.as_deref().unwrap_or_default()),
+                task.recur.map(|r| r.to_string()).unwrap_or_default(),
+                task.archived
+            )?;
+        }
+
+        Ok(tasks.len())
+    }
+
+    /// Export all tasks of a profile as a JSON array.
+    ///
+    /// Uses the same representation as `list --format json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `writer` - Destination for the JSON data
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of tasks written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tasks cannot be read, serialized, or written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let conn = Connection::open("tasks.db")?;
+    /// let file = File::create("tasks.json")?;
+    /// let count = Task::export_json(&conn, "default", file)?;
+    /// ```
+    pub fn export_json<W: Write>(conn: &Connection, profile: &str, mut writer: W) -> Result<usize> {
+        let tasks = Task::list(conn, profile)?;
+
+        serde_json::to_writer_pretty(&mut writer, &tasks)?;
+        writeln!(writer)?;
+
+        Ok(tasks.len())
+    }
+
+    /// Import tasks from CSV.
+    ///
+    /// The first row must be a header naming the columns. Only `description`
+    /// is required; `done`, `birth`, `priority`, `due`, `done_at`, `tags`
+    /// (comma-separated), `notes`, `recur` and `archived` are optional, and any
+    /// other column (such as `id`) is ignored. This accepts the files written
+    /// by `Task::export_csv`.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `reader` - Source of the CSV data
+    /// * `utc` - Store the imported timestamps in UTC instead of local time
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of tasks imported.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is malformed or a database insert fails.
+    /// No tasks are imported when any record is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut conn = Connection::open("tasks.db")?;
+    /// let file = File::open("tasks.csv")?;
+    /// let count = Task::import_csv(&mut conn, "default", file, false)?;
+    /// println!("Imported {} tasks", count);
+    /// ```
+    pub fn import_csv<R: Read>(
+        conn: &mut Connection,
+        profile: &str,
+        mut reader: R,
+        utc: bool,
+    ) -> Result<usize> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+
+        let mut rows = crate::io_utils::parse_csv(&input)?.into_iter();
+        let header = rows.next().context("CSV input is empty")?;
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+        };
+
+        let description_col =
+            column("description").context("CSV header is missing a 'description' column")?;
+        let done_col = column("done");
+        let birth_col = column("birth");
+        let priority_col = column("priority");
+        let due_col = column("due");
+        let done_at_col = column("done_at");
+        let tags_col = column("tags");
+        let notes_col = column("notes");
+        let recur_col = column("recur");
+        let archived_col = column("archived");
+
+        let records = rows
+            .enumerate()
+            .map(|(i, row)| {
+                // Record numbers count the header as record 1
+                let record = i + 2;
+                let field = |col: Option<usize>| {
+                    col.and_then(|c| row.get(c))
+                        .map(|v| v.trim())
+                        .filter(|v| !v.is_empty())
+                };
+
+                let description = row
+                    .get(description_col)
+                    .cloned()
+                    .with_context(|| format!("Missing description in record {}", record))?;
+
+                let done = match field(done_col) {
+                    None => false,
+                    Some(v) if v.eq_ignore_ascii_case("true") || v == "1" => true,
+                    Some(v) if v.eq_ignore_ascii_case("false") || v == "0" => false,
+                    Some(v) => bail!("Invalid done value '{}' in record {}", v, record),
+                };
+
+                let archived = match field(archived_col) {
+                    None => false,
+                    Some(v) if v.eq_ignore_ascii_case("true") || v == "1" => true,
+                    Some(v) if v.eq_ignore_ascii_case("false") || v == "0" => false,
+                    Some(v) => bail!("Invalid archived value '{}' in record {}", v, record),
+                };
+
+                let birth = field(birth_col)
+                    .map(|v| {
+                        parse_timestamp(v).with_context(|| {
+                            format!("Invalid birth timestamp '{}' in record {}", v, record)
+                        })
+                    })
+                    .transpose()?;
+
+                let priority = field(priority_col)
+                    .map(|v| {
+                        v.parse::<u8>().with_context(|| {
+                            format!("Invalid priority '{}' in record {}", v, record)
+                        })
+                    })
+                    .transpose()?
+                    .unwrap_or(0);
+
+                let due = field(due_col)
+                    .map(|v| {
+                        NaiveDate::parse_from_str(v, DATE_FORMAT).with_context(|| {
+                            format!("Invalid due date '{}' in record {}", v, record)
+                        })
+                    })
+                    .transpose()?;
+
+                let done_at = field(done_at_col)
+                    .map(|v| {
+                        parse_timestamp(v).with_context(|| {
+                            format!("Invalid done_at timestamp '{}' in record {}", v, record)
+                        })
+                    })
+                    .transpose()?;
+
+                let tags = field(tags_col)
+                    .map(|v| {
+                        v.split(',')
+                            .map(str::trim)
+                            .filter(|t| !t.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                // Notes keep their whitespace, unlike the other fields
+                let notes = notes_col
+                    .and_then(|c| row.get(c))
+                    .filter(|v| !v.trim().is_empty())
+                    .cloned();
+
+                let recur = field(recur_col)
+                    .map(|v| {
+                        v.parse::<Recurrence>().with_context(|| {
+                            format!("Invalid recurrence '{}' in record {}", v, record)
+                        })
+                    })
+                    .transpose()?;
+
+                Ok(ImportedTask {
+                    description,
+                    done,
+                    birth,
+                    priority,
+                    due,
+                    done_at,
+                    tags,
+                    notes,
+                    recur,
+                    archived,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Task::insert_imported(conn, profile, &records, utc)
+    }
+
+    /// Import tasks from a JSON array.
+    ///
+    /// Each element must be an object with at least a `description` field;
+    /// `done`, `birth`, `priority`, `due`, `done_at`, `tags` (an array of
+    /// strings), `notes`, `recur` and `archived` are optional and `id` is
+    /// ignored.
+    /// This accepts the files written by `Task::export_json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - SQLite database connection
+    /// * `profile` - The profile (task list) to work in
+    /// * `reader` - Source of the JSON data
+    /// * `utc` - Store the imported timestamps in UTC instead of local time
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of tasks imported.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is malformed or a database insert fails.
+    /// No tasks are imported when any record is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut conn = Connection::open("tasks.db")?;
+    /// let file = File::open("tasks.json")?;
+    /// let count = Task::import_json(&mut conn, "default", file, false)?;
+    /// ```
+    pub fn import_json<R: Read>(
+        conn: &mut Connection,
+        profile: &str,
+        reader: R,
+        utc: bool,
+    ) -> Result<usize> {
+        let records: Vec<ImportedTask> =
+            serde_json::from_reader(reader).context("Invalid JSON task data")?;
+
+        Task::insert_imported(conn, profile, &records, utc)
+    }
+
+    /// Insert imported records in a single transaction.
+    ///
+    /// Either every record is inserted or, if any insert fails, none are.
+    fn insert_imported(
+        conn: &mut Connection,
+        profile: &str,
+        records: &[ImportedTask],
+        utc: bool,
+    ) -> Result<usize> {
+        let now = Local::now().naive_local();
+
+        crate::database::with_transaction(conn, |tx| {
+            for (i, record) in records.iter().enumerate() {
+                if record.priority > 3 {
+                    bail!(
+                        "Invalid priority {} for imported task {}, expected 0-3",
+                        record.priority,
+                        i + 1
+                    );
+                }
+
+                let description = normalize_description(&record.description, None)
+                    .with_context(|| format!("Invalid imported task {}", i + 1))?;
+                let birth_str = store_timestamp(record.birth.unwrap_or(now), utc);
+                let due_str = record.due.map(|d| d.format(DATE_FORMAT).to_string());
+                let done_at_str = record
+                    .done_at
+                    .filter(|_| record.done)
+                    .map(|t| store_timestamp(t, utc));
+
+                tx.execute(
+                    crate::database::IMPORT_TASK,
+                    (
+                        &description,
+                        &record.done,
+                        &birth_str,
+                        &record.priority,
+                        &due_str,
+                        &done_at_str,
+                        &record.notes,
+                        &record.recur.map(|r| r.to_string()),
+                        profile,
+                        &record.archived,
+                    ),
+                )?;
+
+                Task::add_tags(tx, tx.last_insert_rowid(), &record.tags)
+                    .with_context(|| format!("Invalid tags for imported task {}", i + 1))?;
+            }
+
+            Ok(records.len())
+        })
+    }
+}
+
+/// Validate and normalize a task description.
+///
+/// Surrounding whitespace is trimmed, and the result must not be empty.
+///
+/// # Arguments
+///
+/// * `description` - The description as given by the user or an import file
+/// * `max_length` - Longest accepted description in characters, if limited
+///
+/// # Returns
+///
+/// Returns the trimmed description.
+///
+/// # Errors
+///
+/// Returns an error if the description is empty or only whitespace, or
+/// longer than `max_length`.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(normalize_description("  Buy milk ", Some(1000))?, "Buy milk");
+/// assert!(normalize_description("   ", None).is_err());
+/// ```
+pub fn normalize_description(description: &str, max_length: Option<usize>) -> Result<String> {
+    let description = description.trim();
+
+    if description.is_empty() {
+        bail!("Task description cannot be empty");
+    }
+    if let Some(max_length) = max_length {
+        let length = description.chars().count();
+        if length > max_length {
+            bail!(
+                "Task description is {} characters long, the limit is {}",
+                length,
+                max_length
+            );
+        }
+    }
+
+    Ok(description.to_string())
+}
+
+/// Validate and normalize a tag name.
+///
+/// Surrounding whitespace is trimmed. Tags are joined with commas when read
+/// back from the database, so a tag can't be empty or contain a comma.
+///
+/// # Arguments
+///
+/// * `tag` - The tag name as given by the user or an import file
+///
+/// # Returns
+///
+/// Returns the trimmed tag name.
+///
+/// # Errors
+///
+/// Returns an error if the tag is empty or contains a comma.
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim();
+
+    if tag.is_empty() {
+        bail!("tag must not be empty");
+    }
+    if tag.contains(',') {
+        bail!("tag '{}' must not contain a comma", tag);
+    }
+
+    Ok(tag.to_string())
+}
+
+/// Format the current time for storage.
+///
+/// # Arguments
+///
+/// * `utc` - Write the time in UTC (`UTC_TIMESTAMP_FORMAT`) instead of local time
+fn now_timestamp(utc: bool) -> String {
+    if utc {
+        Utc::now().format(UTC_TIMESTAMP_FORMAT).to_string()
+    } else {
+        Local::now()
+            .naive_local()
+            .format(TIMESTAMP_FORMAT)
+            .to_string()
+    }
+}
+
+/// Format a local time for storage.
+///
+/// A local time that doesn't exist, because it falls into a daylight
+/// saving gap, can't be converted to UTC and is stored as local time.
+///
+/// # Arguments
+///
+/// * `local` - The time to store, in local time
+/// * `utc` - Write the time in UTC (`UTC_TIMESTAMP_FORMAT`) instead of local time
+fn store_timestamp(local: NaiveDateTime, utc: bool) -> String {
+    match Local.from_local_datetime(&local).earliest() {
+        Some(time) if utc => time
+            .with_timezone(&Utc)
+            .format(UTC_TIMESTAMP_FORMAT)
+            .to_string(),
+        _ => local.format(TIMESTAMP_FORMAT).to_string(),
+    }
+}
+
+/// Parse a timestamp read from the database into local time.
+///
+/// UTC values (`UTC_TIMESTAMP_FORMAT`) are converted to local time; values
+/// without a timezone (`TIMESTAMP_FORMAT`) are already local.
+fn parse_stored_timestamp(value: &str) -> chrono::ParseResult<NaiveDateTime> {
+    if value.ends_with('Z') {
+        NaiveDateTime::parse_from_str(value, UTC_TIMESTAMP_FORMAT)
+            .map(|t| t.and_utc().with_timezone(&Local).naive_local())
+    } else {
+        NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT)
+    }
+}
+
+/// Parse a timestamp read from an import file.
+///
+/// Accepts both the storage format (`2024-12-07 14:30:15`) written by CSV
+/// exports and the ISO-8601 form (`2024-12-07T14:30:15`) used in JSON.
+fn parse_timestamp(value: &str) -> chrono::ParseResult<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT).or_else(|_| value.parse())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::tests::temp_db;
+    use crate::database::with_transaction;
+
+    /// Insert a task with a raw `birth` value, bypassing `Task::add`.
+    fn insert_raw(conn: &Connection, description: &str, birth: &str) -> i64 {
+        conn.execute(
+            "INSERT INTO tasks (description, birth) VALUES (?1, ?2)",
+            (description, birth),
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    /// Add tasks with the given descriptions and return their IDs.
+    fn add_tasks(conn: &Connection, descriptions: &[&str]) -> Vec<i64> {
+        descriptions
+            .iter()
+            .map(|d| Task::add(conn, "default", d.to_string(), None, None, false).unwrap())
+            .collect()
+    }
+
+    /// IDs in the default listing order.
+    fn listed_ids(conn: &Connection) -> Vec<i64> {
+        Task::list_with(conn, "default", &ListOptions::default())
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    /// Stored positions, in listing order.
+    fn positions(conn: &Connection) -> Vec<i64> {
+        let mut stmt = conn
+            .prepare("SELECT position FROM tasks ORDER BY position")
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn list_reports_a_malformed_birth_timestamp() {
+        let (_dir, conn) = temp_db();
+        Task::add(&conn, "default", "Good".to_string(), None, None, false).unwrap();
+        let bad = insert_raw(&conn, "Bad", "yesterday");
+
+        let err = Task::list(&conn, "default").unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            format!("Task {} has an invalid birth timestamp 'yesterday'", bad)
+        );
+    }
+
+    #[test]
+    fn list_returns_every_row_when_timestamps_are_valid() {
+        let (_dir, conn) = temp_db();
+        insert_raw(&conn, "Local", "2024-12-07 14:30:15");
+        insert_raw(&conn, "UTC", "2024-12-07T14:30:15Z");
+
+        let tasks = Task::list(&conn, "default").unwrap();
+
+        let descriptions: Vec<&str> = tasks.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(descriptions, ["Local", "UTC"]);
+    }
+
+    #[test]
+    fn list_with_reports_a_malformed_birth_timestamp() {
+        let (_dir, conn) = temp_db();
+        let bad = insert_raw(&conn, "Bad", "yesterday");
+
+        let err = Task::list_with(&conn, "default", &ListOptions::default()).unwrap_err();
+
+        // The full chain is what `main` prints, parser message included
+        assert_eq!(
+            format!("{:#}", err),
+            format!(
+                "Task {} has an invalid birth timestamp 'yesterday': \
+                 input contains invalid characters",
+                bad
+            )
+        );
+    }
+
+    #[test]
+    fn list_reports_malformed_due_and_done_at_values() {
+        let (_dir, conn) = temp_db();
+        let id = insert_raw(&conn, "Task", "2024-12-07 14:30:15");
+
+        conn.execute("UPDATE tasks SET due = 'soon' WHERE id = ?1", [id])
+            .unwrap();
+        let err = Task::list(&conn, "default").unwrap_err().to_string();
+        assert_eq!(err, format!("Task {} has an invalid due date 'soon'", id));
+
+        conn.execute(
+            "UPDATE tasks SET due = NULL, done = 1, done_at = 'today' WHERE id = ?1",
+            [id],
+        )
+        .unwrap();
+        let err = Task::list(&conn, "default").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            format!("Task {} has an invalid done_at timestamp 'today'", id)
+        );
+    }
+
+    /// IDs listed with the given sort key and direction.
+    fn sorted_ids(conn: &Connection, sort: SortKey, reverse: bool) -> Vec<i64> {
+        let options = ListOptions {
+            sort,
+            reverse,
+            ..ListOptions::default()
+        };
+        Task::list_with(conn, "default", &options)
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    /// Four tasks whose creation times, completion and descriptions each
+    /// give a different order, with the last one moved to the top.
+    fn sortable_tasks(conn: &mut Connection) -> [i64; 4] {
+        let ids = [
+            insert_raw(conn, "banana", "2024-03-01 09:00:00"),
+            insert_raw(conn, "Cherry", "2024-01-01 09:00:00"),
+            insert_raw(conn, "apple", "2024-04-01 09:00:00"),
+            insert_raw(conn, "date", "2024-02-01 09:00:00"),
+        ];
+        conn.execute(
+            "UPDATE tasks SET done = 1 WHERE id IN (?1, ?2)",
+            (ids[0], ids[2]),
+        )
+        .unwrap();
+        with_transaction(conn, |tx| Task::move_to(tx, "default", ids[3], 1)).unwrap();
+        ids
+    }
+
+    #[test]
+    fn list_sorts_by_position_by_default() {
+        let (_dir, mut conn) = temp_db();
+        let [a, b, c, d] = sortable_tasks(&mut conn);
+
+        assert_eq!(sorted_ids(&conn, SortKey::default(), false), [d, a, b, c]);
+    }
+
+    #[test]
+    fn list_sorts_by_id() {
+        let (_dir, mut conn) = temp_db();
+        let [a, b, c, d] = sortable_tasks(&mut conn);
+
+        assert_eq!(sorted_ids(&conn, SortKey::Id, false), [a, b, c, d]);
+    }
+
+    #[test]
+    fn list_sorts_by_birth() {
+        let (_dir, mut conn) = temp_db();
+        let [a, b, c, d] = sortable_tasks(&mut conn);
+
+        assert_eq!(sorted_ids(&conn, SortKey::Birth, false), [b, d, a, c]);
+    }
+
+    #[test]
+    fn list_sorts_by_done_with_ties_in_id_order() {
+        let (_dir, mut conn) = temp_db();
+        let [a, b, c, d] = sortable_tasks(&mut conn);
+
+        assert_eq!(sorted_ids(&conn, SortKey::Done, false), [b, d, a, c]);
+    }
+
+    #[test]
+    fn list_sorts_by_description_ignoring_case() {
+        let (_dir, mut conn) = temp_db();
+        let [a, b, c, d] = sortable_tasks(&mut conn);
+
+        assert_eq!(sorted_ids(&conn, SortKey::Description, false), [c, a, b, d]);
+    }
+
+    #[test]
+    fn list_reverses_the_sort_order() {
+        let (_dir, mut conn) = temp_db();
+        let [a, b, c, d] = sortable_tasks(&mut conn);
+
+        assert_eq!(sorted_ids(&conn, SortKey::Id, true), [d, c, b, a]);
+        assert_eq!(sorted_ids(&conn, SortKey::Description, true), [d, b, a, c]);
+    }
+
+    #[test]
+    fn move_to_the_top() {
+        let (_dir, mut conn) = temp_db();
+        let ids = add_tasks(&conn, &["a", "b", "c", "d"]);
+
+        let moved = with_transaction(&mut conn, |tx| Task::move_to(tx, "default", ids[3], 1));
+
+        assert_eq!(moved.unwrap(), Some(1));
+        assert_eq!(listed_ids(&conn), [ids[3], ids[0], ids[1], ids[2]]);
+        assert_eq!(positions(&conn), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn move_to_the_bottom() {
+        let (_dir, mut conn) = temp_db();
+        let ids = add_tasks(&conn, &["a", "b", "c", "d"]);
+
+        // A position past the end lands on the last one
+        let moved = with_transaction(&mut conn, |tx| Task::move_to(tx, "default", ids[0], 99));
+
+        assert_eq!(moved.unwrap(), Some(4));
+        assert_eq!(listed_ids(&conn), [ids[1], ids[2], ids[3], ids[0]]);
+        assert_eq!(positions(&conn), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn move_to_the_middle() {
+        let (_dir, mut conn) = temp_db();
+        let ids = add_tasks(&conn, &["a", "b", "c", "d"]);
+
+        let moved = with_transaction(&mut conn, |tx| Task::move_to(tx, "default", ids[0], 3));
+
+        assert_eq!(moved.unwrap(), Some(3));
+        assert_eq!(listed_ids(&conn), [ids[1], ids[2], ids[0], ids[3]]);
+        assert_eq!(positions(&conn), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn repeated_moves_keep_positions_unique() {
+        let (_dir, mut conn) = temp_db();
+        let ids = add_tasks(&conn, &["a", "b", "c"]);
+
+        for (id, position) in [(ids[2], 1), (ids[2], 1), (ids[0], 2), (ids[1], 1)] {
+            with_transaction(&mut conn, |tx| Task::move_to(tx, "default", id, position)).unwrap();
+        }
+        let added = add_tasks(&conn, &["d"])[0];
+
+        assert_eq!(listed_ids(&conn), [ids[1], ids[2], ids[0], added]);
+        assert_eq!(positions(&conn), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn move_to_a_missing_task() {
+        let (_dir, mut conn) = temp_db();
+        add_tasks(&conn, &["a"]);
+
+        let moved = with_transaction(&mut conn, |tx| Task::move_to(tx, "default", 42, 1));
+
+        assert_eq!(moved.unwrap(), None);
+    }
+
+    #[test]
+    fn normalize_description_rejects_an_empty_description() {
+        let err = normalize_description("", Some(DEFAULT_MAX_DESCRIPTION_LENGTH)).unwrap_err();
+        assert_eq!(err.to_string(), "Task description cannot be empty");
+    }
+
+    #[test]
+    fn normalize_description_rejects_whitespace_only() {
+        let err = normalize_description(" \t\n ", None).unwrap_err();
+        assert_eq!(err.to_string(), "Task description cannot be empty");
+    }
+
+    #[test]
+    fn normalize_description_trims_a_normal_description() {
+        assert_eq!(
+            normalize_description("  Buy milk\n", Some(DEFAULT_MAX_DESCRIPTION_LENGTH)).unwrap(),
+            "Buy milk"
+        );
+    }
+
+    #[test]
+    fn normalize_description_limits_the_length_in_characters() {
+        // Multi-byte characters count once each
+        assert_eq!(normalize_description("ééé", Some(3)).unwrap(), "ééé");
+
+        let err = normalize_description("éééé", Some(3)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Task description is 4 characters long, the limit is 3"
+        );
+
+        let long = "x".repeat(DEFAULT_MAX_DESCRIPTION_LENGTH * 2);
+        assert!(normalize_description(&long, None).is_ok());
+    }
+
+    #[test]
+    fn import_rejects_an_empty_description() {
+        let (_dir, mut conn) = temp_db();
+        let json = r#"[
+            {"description": "Valid", "done": false, "birth": "2024-12-07T14:30:15"},
+            {"description": "   ", "done": false, "birth": "2024-12-07T14:30:15"}
+        ]"#;
+
+        let err = Task::import_json(&mut conn, "default", json.as_bytes(), false).unwrap_err();
+
+        assert!(
+            format!("{:#}", err).contains("Task description cannot be empty"),
+            "{:#}",
+            err
+        );
+        assert!(Task::list(&conn, "default").unwrap().is_empty());
+    }
+
+    /// Add a task due on the given day that repeats weekly.
+    fn add_weekly(conn: &Connection, due: &str) -> i64 {
+        let due = NaiveDate::parse_from_str(due, DATE_FORMAT).unwrap();
+        let id = Task::add(
+            conn,
+            "default",
+            "Water plants".to_string(),
+            None,
+            Some(due),
+            false,
+        )
+        .unwrap();
+        Task::set_recurrence(conn, id, Recurrence::Weeks(1)).unwrap();
+        id
+    }
+
+    fn mark_done(conn: &mut Connection, id: i64) -> bool {
+        with_transaction(conn, |tx| Task::mark_done(tx, "default", id, false)).unwrap()
+    }
+
+    #[test]
+    fn completing_a_recurring_task_adds_its_next_occurrence() {
+        let (_dir, mut conn) = temp_db();
+        let id = add_weekly(&conn, "2024-12-07");
+
+        assert!(mark_done(&mut conn, id));
+
+        let tasks = Task::list(&conn, "default").unwrap();
+        assert_eq!(tasks.len(), 2);
+        let next = &tasks[1];
+        assert!(!next.done);
+        assert_eq!(next.description, "Water plants");
+        assert_eq!(next.due.unwrap().to_string(), "2024-12-14");
+        assert_eq!(next.recur, Some(Recurrence::Weeks(1)));
+    }
+
+    #[test]
+    fn completing_a_recurring_task_again_adds_no_second_occurrence() {
+        let (_dir, mut conn) = temp_db();
+        let id = add_weekly(&conn, "2024-12-07");
+
+        assert!(mark_done(&mut conn, id));
+        assert!(Task::mark_undone(&conn, "default", id).unwrap());
+        assert!(mark_done(&mut conn, id));
+
+        let pending: Vec<Task> = Task::list(&conn, "default")
+            .unwrap()
+            .into_iter()
+            .filter(|task| !task.done)
+            .collect();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].due.unwrap().to_string(), "2024-12-14");
+    }
+
+    #[test]
+    fn completing_a_recurring_task_again_replaces_a_removed_occurrence() {
+        let (_dir, mut conn) = temp_db();
+        let id = add_weekly(&conn, "2024-12-07");
+        mark_done(&mut conn, id);
+        Task::mark_undone(&conn, "default", id).unwrap();
+        let next = Task::list(&conn, "default").unwrap()[1].id;
+        conn.execute("DELETE FROM tasks WHERE id = ?1", [next])
+            .unwrap();
+
+        mark_done(&mut conn, id);
+
+        let tasks = Task::list(&conn, "default").unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(!tasks[1].done);
+    }
+
+    #[test]
+    fn copies_of_a_subtask_keep_its_parent() {
+        let (_dir, mut conn) = temp_db();
+        let parent = add_tasks(&conn, &["Garden"])[0];
+        let subtask = add_weekly(&conn, "2024-12-07");
+        Task::set_parent(&conn, subtask, parent).unwrap();
+
+        let copy = Task::duplicate(&conn, "default", subtask, false)
+            .unwrap()
+            .unwrap();
+        mark_done(&mut conn, subtask);
+
+        let tasks = Task::list(&conn, "default").unwrap();
+        assert_eq!(tasks.len(), 4);
+        let next = tasks.last().unwrap();
+        assert_ne!(next.id, copy);
+        assert_eq!(next.parent_id, Some(parent));
+        let copy = Task::get(&conn, "default", copy).unwrap().unwrap();
+        assert_eq!(copy.parent_id, Some(parent));
+    }
+
+    /// Descriptions and depths in tree order.
+    fn tree(conn: &Connection) -> Vec<(String, usize)> {
+        Task::list_tree(conn, "default", &ListOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|task| (task.description, task.depth))
+            .collect()
+    }
+
+    #[test]
+    fn list_tree_lists_subtasks_under_their_parent() {
+        let (_dir, conn) = temp_db();
+        let [a, b, a1, b1, a1x, a2]: [i64; 6] =
+            add_tasks(&conn, &["a", "b", "a1", "b1", "a1x", "a2"])
+                .try_into()
+                .unwrap();
+        for (child, parent) in [(a1, a), (b1, b), (a1x, a1), (a2, a)] {
+            Task::set_parent(&conn, child, parent).unwrap();
+        }
+
+        let expected = [
+            ("a", 0),
+            ("a1", 1),
+            ("a1x", 2),
+            ("a2", 1),
+            ("b", 0),
+            ("b1", 1),
+        ];
+        assert_eq!(
+            tree(&conn),
+            expected.map(|(description, depth)| (description.to_string(), depth))
+        );
+    }
+
+    #[test]
+    fn removing_a_parent_makes_its_subtasks_top_level() {
+        let (_dir, mut conn) = temp_db();
+        let [parent, child, grandchild]: [i64; 3] =
+            add_tasks(&conn, &["parent", "child", "grandchild"])
+                .try_into()
+                .unwrap();
+        Task::set_parent(&conn, child, parent).unwrap();
+        Task::set_parent(&conn, grandchild, child).unwrap();
+
+        assert!(with_transaction(&mut conn, |tx| Task::remove(tx, "default", parent)).unwrap());
+
+        let child = Task::get(&conn, "default", child).unwrap().unwrap();
+        assert_eq!(child.parent_id, None);
+        let grandchild = Task::get(&conn, "default", grandchild).unwrap().unwrap();
+        assert_eq!(grandchild.parent_id, Some(child.id));
+        assert_eq!(
+            tree(&conn),
+            [("child".to_string(), 0), ("grandchild".to_string(), 1)]
+        );
     }
 }
@@ -0,0 +1,204 @@
+//! Recurrence interval module.
+//!
+//! This module parses the intervals of recurring tasks, such as `daily`,
+//! `weekly` or `7d`, and computes the date of a task's next occurrence.
+//! Intervals are stored in the database in their canonical text form, as
+//! produced by `Display`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use chrono::{Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// How often a recurring task comes back.
+///
+/// - `Days(n)`: Every `n` days, written `daily` or `<n>d`
+/// - `Weeks(n)`: Every `n` weeks, written `weekly` or `<n>w`
+/// - `Months(n)`: Every `n` months, written `monthly` or `<n>m`
+///
+/// `n` is always at least 1. Intervals serialize to JSON as their text form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Recurrence {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+}
+
+impl Recurrence {
+    /// Get the date one interval after `date`.
+    ///
+    /// Monthly intervals keep the day of the month where possible and fall
+    /// back to the last day of shorter months, so a task due on January 31st
+    /// comes back on the last day of February.
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - The date to advance
+    ///
+    /// # Returns
+    ///
+    /// Returns the advanced date, or `None` if it would be out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+    /// assert_eq!(
+    ///     Recurrence::Months(1).next_date(date),
+    ///     NaiveDate::from_ymd_opt(2024, 2, 29)
+    /// );
+    /// ```
+    pub fn next_date(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Days(n) => date.checked_add_days(Days::new(u64::from(n))),
+            Recurrence::Weeks(n) => date.checked_add_days(Days::new(u64::from(n) * 7)),
+            Recurrence::Months(n) => date.checked_add_months(Months::new(n)),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = Error;
+
+    /// Parse an interval such as `daily`, `weekly`, `monthly`, `7d`, `2w` or
+    /// `3m`, ignoring case and surrounding whitespace.
+    fn from_str(value: &str) -> Result<Recurrence> {
+        let interval = value.trim().to_ascii_lowercase();
+
+        let recurrence = match interval.as_str() {
+            "daily" => Recurrence::Days(1),
+            "weekly" => Recurrence::Weeks(1),
+            "monthly" => Recurrence::Months(1),
+            _ => {
+                let split = interval.len().saturating_sub(1);
+                // `parse` alone would also accept a sign, as in `+7d`
+                let count = interval
+                    .get(..split)
+                    .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .filter(|&n| n > 0);
+
+                match (count, interval.get(split..)) {
+                    (Some(n), Some("d")) => Recurrence::Days(n),
+                    (Some(n), Some("w")) => Recurrence::Weeks(n),
+                    (Some(n), Some("m")) => Recurrence::Months(n),
+                    _ => bail!(
+                        "invalid interval '{}', expected daily, weekly, monthly or a count \
+                         of days, weeks or months such as 7d, 2w or 3m",
+                        value
+                    ),
+                }
+            }
+        };
+
+        Ok(recurrence)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Days(1) => write!(f, "daily"),
+            Recurrence::Weeks(1) => write!(f, "weekly"),
+            Recurrence::Months(1) => write!(f, "monthly"),
+            Recurrence::Days(n) => write!(f, "{}d", n),
+            Recurrence::Weeks(n) => write!(f, "{}w", n),
+            Recurrence::Months(n) => write!(f, "{}m", n),
+        }
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Recurrence> {
+        value.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> String {
+        recurrence.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_named_intervals() {
+        assert_eq!("daily".parse::<Recurrence>().unwrap(), Recurrence::Days(1));
+        assert_eq!(
+            "weekly".parse::<Recurrence>().unwrap(),
+            Recurrence::Weeks(1)
+        );
+        assert_eq!(
+            " Monthly ".parse::<Recurrence>().unwrap(),
+            Recurrence::Months(1)
+        );
+    }
+
+    #[test]
+    fn parses_counted_intervals() {
+        assert_eq!("7d".parse::<Recurrence>().unwrap(), Recurrence::Days(7));
+        assert_eq!("2w".parse::<Recurrence>().unwrap(), Recurrence::Weeks(2));
+        assert_eq!("3M".parse::<Recurrence>().unwrap(), Recurrence::Months(3));
+    }
+
+    #[test]
+    fn rejects_invalid_intervals() {
+        for value in ["0d", "d", "x", "", "+7d", "-7d", " 7 d", "7y", "1.5w", "é"] {
+            let err = value.parse::<Recurrence>().unwrap_err().to_string();
+            assert!(err.starts_with("invalid interval"), "{:?}: {}", value, err);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for recurrence in [
+            Recurrence::Days(1),
+            Recurrence::Weeks(1),
+            Recurrence::Months(1),
+            Recurrence::Days(10),
+            Recurrence::Weeks(2),
+            Recurrence::Months(6),
+        ] {
+            let text = recurrence.to_string();
+            assert_eq!(text.parse::<Recurrence>().unwrap(), recurrence, "{}", text);
+        }
+        assert_eq!(Recurrence::Days(1).to_string(), "daily");
+        assert_eq!(Recurrence::Weeks(2).to_string(), "2w");
+    }
+
+    #[test]
+    fn next_date_advances_by_the_interval() {
+        assert_eq!(
+            Recurrence::Days(3).next_date(date(2024, 12, 30)),
+            Some(date(2025, 1, 2))
+        );
+        assert_eq!(
+            Recurrence::Weeks(2).next_date(date(2024, 12, 7)),
+            Some(date(2024, 12, 21))
+        );
+    }
+
+    #[test]
+    fn monthly_falls_back_to_the_end_of_shorter_months() {
+        assert_eq!(
+            Recurrence::Months(1).next_date(date(2024, 1, 31)),
+            Some(date(2024, 2, 29))
+        );
+        assert_eq!(
+            Recurrence::Months(1).next_date(date(2023, 1, 31)),
+            Some(date(2023, 2, 28))
+        );
+    }
+}